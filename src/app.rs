@@ -1,13 +1,21 @@
 use eframe::egui::{self, CentralPanel, ProgressBar, ScrollArea};
-use log::{error, info};
+use log::{error, info, warn, Level, LevelFilter};
 use serde::Deserialize;
-use std::process::Command;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use ureq::Agent;
 
-use crate::config;
-use crate::modules::addon_manager;
+use crate::cli;
+use crate::config::{self, GameProfile, Manifest, ManifestSource, Settings};
+use crate::modules::cancellation::CancellationToken;
+use crate::modules::log_buffer::{self, LogRecord};
+use crate::modules::operations::{self, AddonEntry, Operation, Step};
+use crate::modules::queue::{JobStatus, OperationQueue};
+use crate::modules::self_update::{self, Release, UpdateStatus};
+use crate::modules::{addon_manager, game_cache, launcher, realmlist};
 
 #[derive(Clone, Deserialize)]
 pub struct Addon {
@@ -15,6 +23,11 @@ pub struct Addon {
     pub link: String,
     pub description: String,
     pub target_path: String,
+    pub version_url: Option<String>,
+    pub version_file: Option<String>,
+    pub sha256: Option<String>,
+    pub depends: Vec<String>,
+    pub conflicts: Vec<String>,
 }
 
 #[derive(Default)]
@@ -22,116 +35,897 @@ pub struct AddonState {
     pub target_state: Option<bool>,
     pub installing: bool,
     pub progress: f32,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    // Байт в секунду
+    pub speed: f64,
     pub needs_update: bool,
+    pub installed_version: Option<String>,
+    pub remote_version: Option<String>,
+    pub error: Option<String>,
+    pub last_operation: Option<Operation>,
+    pub cancel: CancellationToken,
+}
+
+impl AddonState {
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total?.checked_sub(self.downloaded)?;
+        (self.speed > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / self.speed))
+    }
+}
+
+// Аддоны хранятся по имени: пока окно открыто, список может перезагрузиться
+pub enum Confirmation {
+    Uninstall {
+        names: Vec<String>,
+        dependents: Vec<String>,
+    },
+    Conflict {
+        name: String,
+        conflicting: Vec<String>,
+    },
 }
 
 pub struct App {
-    pub addons: Vec<(Addon, Arc<Mutex<AddonState>>)>,
+    pub addons: Vec<AddonEntry>,
     pub client: Agent,
+    queue: OperationQueue,
+    selected: HashSet<String>,
     pub game_available: bool,
-    last_nsqc_check: Instant,
-    nsqc_check_interval: Duration,
+    pub settings: Settings,
+    pub manifest_source: ManifestSource,
+    manifest_input: String,
+    profile_name_input: String,
+    game_dir_input: String,
+    game_dir_error: Option<String>,
+    launch_error: Option<String>,
+    realmlist: Option<String>,
+    realmlist_input: String,
+    realmlist_error: Option<String>,
+    cache_status: Option<Result<u64, String>>,
+    offline_since: Option<u64>,
+    manifest_error: Option<String>,
+    last_update_check: Instant,
+    update_check_interval: Duration,
+    update_check_running: Arc<AtomicBool>,
+    pending_confirmation: Option<Confirmation>,
+    update_status: Arc<Mutex<UpdateStatus>>,
+    update_progress: Arc<Mutex<AddonState>>,
+    release_feed_input: String,
+    log_level: LevelFilter,
     initial_size_set: bool,
 }
 
 impl App {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, args: cli::Args) -> Self {
         cc.egui_ctx.set_visuals(egui::Visuals::dark());
 
-        let game_available = config::check_game_directory().is_ok();
+        let mut settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        settings.migrate();
 
-        let client = ureq::AgentBuilder::new()
-            .timeout_connect(std::time::Duration::from_secs(30))
-            .build();
+        if let Some(name) = &args.profile {
+            if settings.profile(name).is_some() {
+                settings.active_profile = Some(name.clone());
+            } else {
+                warn!("Unknown profile requested: {}", name);
+            }
+        }
+        let profile = settings.active().cloned();
 
-        let addons =
-            config::load_addons_config_blocking(&client).expect("Failed to load addons config");
+        let manifest_source = config::resolve_manifest_source(
+            args.manifest.as_deref(),
+            settings.manifest_source.as_deref(),
+        );
+        info!("Using manifest source: {}", manifest_source);
 
-        let addons_with_state = addons
-            .into_iter()
-            .map(|(_, addon)| {
-                let installed = addon_manager::check_addon_installed(&addon);
-                let mut needs_update = false;
-
-                if addon.name == "NSQC" && installed {
-                    needs_update = addon_manager::check_nsqc_update(&client).unwrap_or(false);
-                }
-
-                (
-                    addon,
-                    Arc::new(Mutex::new(AddonState {
-                        target_state: Some(installed),
-                        installing: false,
-                        progress: 0.0,
-                        needs_update,
-                    })),
-                )
-            })
-            .collect();
+        let game_dir = config::resolve_game_dir(
+            args.game_dir.as_deref(),
+            profile.as_ref().map(|profile| profile.game_dir.as_path()),
+        );
+        config::set_base_dir(game_dir);
+        let base_dir = config::base_dir();
+        info!("Using game directory: {}", base_dir.display());
+
+        let game_available = config::check_game_directory(&base_dir).is_ok();
+
+        let client = config::http_client();
 
-        Self {
-            addons: addons_with_state,
+        let mut app = Self {
+            addons: Vec::new(),
+            queue: OperationQueue::new(client.clone(), settings.parallel_jobs),
+            selected: HashSet::new(),
             client,
             game_available,
-            last_nsqc_check: Instant::now() - Duration::from_secs(30),
-            nsqc_check_interval: Duration::from_secs(30),
+            settings,
+            manifest_input: manifest_source.to_string(),
+            manifest_source,
+            profile_name_input: profile
+                .map(|profile| profile.name)
+                .unwrap_or_else(|| config::DEFAULT_PROFILE_NAME.to_string()),
+            game_dir_input: base_dir.display().to_string(),
+            game_dir_error: None,
+            launch_error: None,
+            realmlist: None,
+            realmlist_input: realmlist::current().unwrap_or_default(),
+            realmlist_error: None,
+            cache_status: None,
+            offline_since: None,
+            manifest_error: None,
+            last_update_check: Instant::now() - Duration::from_secs(30),
+            update_check_interval: Duration::from_secs(30),
+            update_check_running: Arc::new(AtomicBool::new(false)),
+            pending_confirmation: None,
+            update_status: Arc::new(Mutex::new(UpdateStatus::Idle)),
+            update_progress: Arc::default(),
+            release_feed_input: String::new(),
+            log_level: LevelFilter::Info,
             initial_size_set: false,
+        };
+        app.release_feed_input = app.settings.release_feed().to_string();
+        app.reload_manifest();
+
+        self_update::cleanup();
+        app.check_self_update();
+        app
+    }
+
+    fn reload_manifest(&mut self) {
+        match config::load_addons_config_blocking(&self.client, &self.manifest_source) {
+            Ok(manifest) => self.set_manifest(manifest),
+            Err(e) => {
+                error!("Failed to load addons config: {}", e);
+                self.manifest_error = Some(e.to_string());
+            }
         }
     }
 
-    fn check_nsqc_update(&mut self) {
-        if let Some((_addon, state)) = self.addons.iter_mut().find(|(a, _)| a.name == "NSQC") {
-            let mut state = state.lock().unwrap();
-            if state.installing {
-                return;
+    fn set_manifest(&mut self, manifest: Manifest) {
+        if let Some(saved_at) = manifest.cached_at {
+            warn!(
+                "Offline mode, using cached manifest from {}",
+                config::format_timestamp(saved_at)
+            );
+        }
+
+        self.offline_since = manifest.cached_at;
+        self.manifest_error = None;
+        self.realmlist = manifest.realmlist;
+        self.selected.clear();
+        self.pending_confirmation = None;
+        self.addons = operations::build_entries(manifest.addons);
+        self.last_update_check = Instant::now() - self.update_check_interval;
+    }
+
+    fn validate_profile(&mut self, name: &str, dir: &Path, current: Option<usize>) -> bool {
+        let duplicate = self
+            .settings
+            .profiles
+            .iter()
+            .enumerate()
+            .any(|(index, profile)| Some(index) != current && profile.name == name);
+
+        let error = if name.is_empty() {
+            Some("Укажите название профиля".to_string())
+        } else if duplicate {
+            Some(format!("Профиль {} уже есть", name))
+        } else if let Err(e) = config::check_game_directory(dir) {
+            warn!("Rejected game directory: {}", e);
+            Some(format!("В папке {} нет Wow.exe", dir.display()))
+        } else {
+            None
+        };
+
+        let valid = error.is_none();
+        self.game_dir_error = error;
+        valid
+    }
+
+    fn apply_game_dir(&mut self) {
+        let name = self.profile_name_input.trim().to_string();
+        let dir = PathBuf::from(self.game_dir_input.trim());
+        let current = self.settings.active_index();
+
+        if !self.validate_profile(&name, &dir, current) {
+            return;
+        }
+
+        info!("Profile {} uses game directory: {}", name, dir.display());
+        match current {
+            Some(index) => {
+                let profile = &mut self.settings.profiles[index];
+                profile.name = name.clone();
+                profile.game_dir = dir.clone();
             }
+            None => self.settings.profiles.push(GameProfile {
+                name: name.clone(),
+                game_dir: dir.clone(),
+                ..Default::default()
+            }),
+        }
+        self.settings.active_profile = Some(name);
+        self.activate_game_dir(dir);
+    }
+
+    fn add_profile(&mut self) {
+        let name = self.profile_name_input.trim().to_string();
+        let dir = PathBuf::from(self.game_dir_input.trim());
+
+        if !self.validate_profile(&name, &dir, None) {
+            return;
+        }
+
+        info!("Added profile {}: {}", name, dir.display());
+        self.settings.profiles.push(GameProfile {
+            name: name.clone(),
+            game_dir: dir.clone(),
+            ..Default::default()
+        });
+        self.settings.active_profile = Some(name);
+        self.activate_game_dir(dir);
+    }
+
+    fn remove_profile(&mut self) {
+        let Some(index) = self.settings.active_index() else {
+            return;
+        };
+
+        let removed = self.settings.profiles.remove(index);
+        info!("Removed profile {}", removed.name);
+        self.settings.active_profile = None;
+
+        if self.settings.profiles.is_empty() {
+            self.profile_name_input = config::DEFAULT_PROFILE_NAME.to_string();
+        } else {
+            self.select_profile(0);
+        }
+    }
 
-            match addon_manager::check_nsqc_update(&self.client) {
-                Ok(needs_update) => state.needs_update = needs_update,
-                Err(e) => error!("NSQC version check failed: {}", e),
+    fn select_profile(&mut self, index: usize) {
+        let profile = self.settings.profiles[index].clone();
+        info!("Switched to profile {}", profile.name);
+
+        self.settings.active_profile = Some(profile.name.clone());
+        self.profile_name_input = profile.name;
+        self.game_dir_input = profile.game_dir.display().to_string();
+        self.game_dir_error = None;
+        self.pending_confirmation = None;
+        self.activate_game_dir(profile.game_dir);
+    }
+
+    // Реестр установленных файлов и загрузки лежат в папке игры, поэтому у каждого профиля свои
+    fn activate_game_dir(&mut self, dir: PathBuf) {
+        config::set_base_dir(Some(dir));
+        self.game_available = config::check_game_directory(&config::base_dir()).is_ok();
+        self.realmlist_input = realmlist::current().unwrap_or_default();
+        self.realmlist_error = None;
+        self.cache_status = None;
+        self.rebuild_states();
+    }
+
+    fn apply_realmlist(&mut self, address: String) {
+        match realmlist::apply(&address) {
+            Ok(()) => {
+                self.realmlist_input = address;
+                self.realmlist_error = None;
+            }
+            Err(e) => {
+                error!("Failed to update realmlist: {:?}", e);
+                self.realmlist_error = Some(operations::describe_error(&e));
             }
         }
     }
 
-    fn toggle_addon(&mut self, index: usize) {
-        let (addon, state) = self.addons[index].clone();
+    #[cfg(not(target_os = "android"))]
+    fn pick_game_dir(&mut self) {
+        if let Some(dir) = rfd::FileDialog::new()
+            .set_directory(config::base_dir())
+            .pick_folder()
+        {
+            self.game_dir_input = dir.display().to_string();
+            self.apply_game_dir();
+        }
+    }
+
+    // Состояние аддонов зависит от папки игры, а список из манифеста — нет
+    fn rebuild_states(&mut self) {
+        let addons = self
+            .addons
+            .iter()
+            .map(|(addon, _)| (addon.name.clone(), addon.clone()))
+            .collect();
+        self.addons = operations::build_entries(addons);
+        self.last_update_check = Instant::now() - self.update_check_interval;
+    }
+
+    fn apply_manifest_source(&mut self) {
+        let source = ManifestSource::parse(&self.manifest_input);
+
+        match config::load_addons_config_blocking(&self.client, &source) {
+            Ok(manifest) => {
+                info!("Manifest source changed: {}", source);
+                self.settings.manifest_source = Some(source.to_string());
+                self.manifest_input = source.to_string();
+                self.manifest_source = source;
+                self.set_manifest(manifest);
+            }
+            Err(e) => error!("Failed to load manifest from {}: {}", source, e),
+        }
+    }
+
+    fn check_self_update(&self) {
         let client = self.client.clone();
+        let feed_url = self.settings.release_feed().to_string();
+        let status = self.update_status.clone();
 
         std::thread::spawn(move || {
-            let mut state_lock = state.lock().unwrap();
-            let current_state = addon_manager::check_addon_installed(&addon);
-            let desired_state = !current_state;
+            let result = match self_update::check(&client, &feed_url) {
+                Ok(Some(release)) => UpdateStatus::Available(release),
+                Ok(None) => UpdateStatus::Idle,
+                Err(e) => {
+                    warn!("Self-update check failed: {}", e);
+                    UpdateStatus::Idle
+                }
+            };
+            *status.lock().unwrap() = result;
+        });
+    }
 
-            state_lock.installing = true;
-            state_lock.target_state = Some(desired_state);
-            state_lock.progress = 0.0;
-            drop(state_lock);
+    fn install_self_update(&self, release: Release) {
+        let client = self.client.clone();
+        let status = self.update_status.clone();
+        let progress = self.update_progress.clone();
+        *progress.lock().unwrap() = AddonState::default();
+        *status.lock().unwrap() = UpdateStatus::Installing;
 
-            let result = if desired_state {
-                addon_manager::install_addon(&client, &addon, state.clone())
-            } else {
-                addon_manager::uninstall_addon(&addon)
+        std::thread::spawn(move || {
+            let token = progress.lock().unwrap().cancel.clone();
+            let result = match self_update::install(&client, &release, progress, &token) {
+                Ok(()) => UpdateStatus::Installed(release.version),
+                Err(e) => {
+                    error!("Self-update failed: {:?}", e);
+                    UpdateStatus::Failed(operations::describe_error(&e))
+                }
             };
+            *status.lock().unwrap() = result;
+        });
+    }
+
+    fn restart(&self, ctx: &egui::Context) {
+        match self_update::restart() {
+            Ok(()) => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Err(e) => {
+                error!("Restart failed: {:?}", e);
+                *self.update_status.lock().unwrap() = UpdateStatus::Failed(e.to_string());
+            }
+        }
+    }
+
+    fn rollback_self_update(&self, ctx: &egui::Context) {
+        match self_update::rollback() {
+            Ok(()) => self.restart(ctx),
+            Err(e) => {
+                error!("Rollback failed: {:?}", e);
+                *self.update_status.lock().unwrap() = UpdateStatus::Failed(e.to_string());
+            }
+        }
+    }
+
+    fn apply_release_feed(&mut self) {
+        let feed_url = self.release_feed_input.trim();
+        self.settings.release_feed = (!feed_url.is_empty()
+            && feed_url != config::DEFAULT_RELEASE_FEED_URL)
+            .then(|| feed_url.to_string());
+        self.check_self_update();
+    }
+
+    fn show_self_update(&mut self, ui: &mut egui::Ui) {
+        let status = self.update_status.lock().unwrap().clone();
+        let busy = self.is_busy();
+
+        match status {
+            UpdateStatus::Idle => {}
+            UpdateStatus::Available(release) => {
+                ui.horizontal(|ui| {
+                    let label = ui.colored_label(
+                        egui::Color32::GREEN,
+                        format!(
+                            "⬆ Доступна версия {} (у вас {})",
+                            release.version,
+                            self_update::CURRENT_VERSION
+                        ),
+                    );
+                    if let Some(notes) = &release.notes {
+                        label.on_hover_text(notes);
+                    }
+                    if ui.button("Обновить программу").clicked() {
+                        self.install_self_update(release.clone());
+                    }
+                });
+            }
+            UpdateStatus::Installing => {
+                ui.horizontal(|ui| {
+                    ui.label("Обновление программы:");
+                    show_progress(ui, &self.update_progress.lock().unwrap());
+                });
+            }
+            UpdateStatus::Installed(version) => {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::GREEN,
+                        format!("✅ Версия {} установлена", version),
+                    );
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("🔄 Перезапустить"))
+                        .clicked()
+                    {
+                        self.restart(ui.ctx());
+                    }
+                });
+            }
+            UpdateStatus::Failed(e) => {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("❌ Не удалось обновить программу: {}", e),
+                    );
+                    if ui.button("Скрыть").clicked() {
+                        *self.update_status.lock().unwrap() = UpdateStatus::Idle;
+                    }
+                });
+            }
+        }
+    }
+
+    fn clear_cache(&mut self) {
+        let result = game_cache::clear();
+        if let Err(e) = &result {
+            error!("Failed to clear cache: {:?}", e);
+        }
+        self.cache_status = Some(result.map_err(|e| operations::describe_error(&e)));
+    }
+
+    fn launch_game(&mut self) {
+        let profile = self.settings.active().cloned().unwrap_or_default();
 
-            if addon.name == "NSQC" {
-                if let Ok(needs_update) = addon_manager::check_nsqc_update(&client) {
-                    let mut state = state.lock().unwrap();
-                    state.needs_update = needs_update;
+        if let Some(address) = self.realmlist.clone().filter(|_| profile.apply_realmlist) {
+            self.apply_realmlist(address);
+            if let Some(error) = &self.realmlist_error {
+                self.launch_error = Some(error.clone());
+                return;
+            }
+        }
+
+        if profile.clear_cache {
+            self.clear_cache();
+            if let Some(Err(error)) = &self.cache_status {
+                self.launch_error = Some(error.clone());
+                return;
+            }
+        }
+
+        match launcher::launch_game(&profile.launch) {
+            Ok(()) => {
+                info!("Game launched successfully");
+                self.launch_error = None;
+            }
+            Err(e) => {
+                error!("Failed to launch game: {:?}", e);
+                self.launch_error = Some(operations::describe_error(&e));
+            }
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.addons
+            .iter()
+            .any(|(_, state)| state.lock().unwrap().installing)
+    }
+
+    fn check_updates(&mut self) {
+        if self.update_check_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let addons: Vec<_> = self
+            .addons
+            .iter()
+            .filter(|(addon, _)| addon.version_url.is_some())
+            .cloned()
+            .collect();
+        let client = self.client.clone();
+        let running = self.update_check_running.clone();
+
+        std::thread::spawn(move || {
+            for (addon, state) in addons {
+                if state.lock().unwrap().installing {
+                    continue;
+                }
+
+                if let Err(e) = addon_manager::refresh_update_state(&client, &addon, &state) {
+                    error!("Version check failed: {} - {}", addon.name, e);
                 }
             }
+            running.store(false, Ordering::SeqCst);
+        });
+    }
 
-            let mut state = state.lock().unwrap();
-            state.installing = false;
-            state.target_state = Some(addon_manager::check_addon_installed(&addon));
+    fn toggle_addon(&mut self, index: usize) {
+        if addon_manager::check_addon_installed(&self.addons[index].0) {
+            self.uninstall_addon(index);
+        } else {
+            self.install_addon(index);
+        }
+    }
+
+    fn update_addon(&mut self, index: usize) {
+        self.install_addon(index);
+    }
+
+    fn install_addon(&mut self, index: usize) {
+        match operations::install_plan(&self.addons, index) {
+            Ok((steps, conflicting)) if conflicting.is_empty() => {
+                let title = self.install_title(index);
+                self.run_steps(title, steps);
+            }
+            Ok((_, conflicting)) => {
+                self.pending_confirmation = Some(Confirmation::Conflict {
+                    name: self.addons[index].0.name.clone(),
+                    conflicting: self.names(&conflicting),
+                });
+            }
+            Err(e) => self.report_plan_error(index, e),
+        }
+    }
+
+    fn install_title(&self, index: usize) -> String {
+        let name = &self.addons[index].0.name;
+        if operations::is_installed(&self.addons, index) {
+            format!("Обновление {}", name)
+        } else {
+            format!("Установка {}", name)
+        }
+    }
+
+    fn report_plan_error(&self, index: usize, e: anyhow::Error) {
+        let (addon, state) = &self.addons[index];
+        error!("Dependency resolution failed: {} - {}", addon.name, e);
+
+        let mut state = state.lock().unwrap();
+        state.error = Some(e.to_string());
+        state.last_operation = Some(Operation::Install);
+    }
+
+    fn retry_addon(&mut self, index: usize) {
+        let operation = self.addons[index].1.lock().unwrap().last_operation;
+        match operation {
+            Some(Operation::Install) => self.install_addon(index),
+            Some(Operation::Uninstall) => self.uninstall_addon(index),
+            None => {}
+        }
+    }
+
+    fn dismiss_error(&self, index: usize) {
+        self.addons[index].1.lock().unwrap().error = None;
+    }
+
+    fn uninstall_addon(&mut self, index: usize) {
+        self.uninstall_addons(vec![index]);
+    }
+
+    // Подтверждение нужно, только если удаляемые аддоны нужны кому-то ещё
+    fn uninstall_addons(&mut self, indices: Vec<usize>) {
+        let names: Vec<&str> = indices
+            .iter()
+            .map(|&i| self.addons[i].0.name.as_str())
+            .collect();
+        let mut dependents: Vec<String> = indices
+            .iter()
+            .flat_map(|&i| operations::installed_dependents(&self.addons, i))
+            .filter(|name| !names.contains(&name.as_str()))
+            .collect();
+        dependents.sort();
+        dependents.dedup();
+
+        if dependents.is_empty() {
+            self.queue_uninstall(&indices);
+        } else {
+            self.pending_confirmation = Some(Confirmation::Uninstall {
+                names: self.names(&indices),
+                dependents,
+            });
+        }
+    }
+
+    fn queue_uninstall(&mut self, indices: &[usize]) {
+        for &index in indices {
+            let title = format!("Удаление {}", self.addons[index].0.name);
+            let step = operations::step(&self.addons, index, Operation::Uninstall);
+            self.run_steps(title, vec![step]);
+        }
+    }
 
-            if let Err(e) = result {
-                error!("Operation failed: {} - {:?}", addon.name, e);
+    fn update_all(&mut self) {
+        let outdated: Vec<usize> = (0..self.addons.len())
+            .filter(|&i| {
+                let state = self.addons[i].1.lock().unwrap();
+                state.needs_update && !state.installing
+            })
+            .collect();
+
+        for index in outdated {
+            self.queue_install(index);
+        }
+    }
+
+    fn install_selected(&mut self) {
+        for index in self.take_selected() {
+            if !operations::is_installed(&self.addons, index) {
+                self.queue_install(index);
             }
+        }
+    }
+
+    fn remove_selected(&mut self) {
+        let indices: Vec<usize> = self
+            .take_selected()
+            .into_iter()
+            .filter(|&i| operations::is_installed(&self.addons, i))
+            .collect();
+
+        if !indices.is_empty() {
+            self.uninstall_addons(indices);
+        }
+    }
+
+    fn take_selected(&mut self) -> Vec<usize> {
+        let selected = std::mem::take(&mut self.selected);
+        (0..self.addons.len())
+            .filter(|&i| selected.contains(&self.addons[i].0.name))
+            .collect()
+    }
+
+    // В пакетном режиме несовместимые аддоны не удаляются без спроса, а пропускаются
+    fn queue_install(&mut self, index: usize) {
+        match operations::install_plan(&self.addons, index) {
+            Ok((steps, conflicting)) if conflicting.is_empty() => {
+                let title = self.install_title(index);
+                self.run_steps(title, steps);
+            }
+            Ok((_, conflicting)) => {
+                let names: Vec<&str> = conflicting
+                    .iter()
+                    .map(|&i| self.addons[i].0.name.as_str())
+                    .collect();
+                let e = anyhow::anyhow!(
+                    "Несовместим с установленными: {}. Установите его отдельно",
+                    names.join(", ")
+                );
+                self.report_plan_error(index, e);
+            }
+            Err(e) => self.report_plan_error(index, e),
+        }
+    }
+
+    fn names(&self, indices: &[usize]) -> Vec<String> {
+        indices
+            .iter()
+            .map(|&i| self.addons[i].0.name.clone())
+            .collect()
+    }
+
+    fn indices(&self, names: &[String]) -> Vec<usize> {
+        names
+            .iter()
+            .filter_map(|name| operations::index_of(&self.addons, name))
+            .collect()
+    }
+
+    fn apply_confirmation(&mut self, confirmation: Confirmation) {
+        match confirmation {
+            Confirmation::Uninstall { names, .. } => {
+                let indices = self.indices(&names);
+                self.queue_uninstall(&indices);
+            }
+            Confirmation::Conflict { name, conflicting } => {
+                let Some(index) = operations::index_of(&self.addons, &name) else {
+                    warn!("Addon {} is no longer in the list", name);
+                    return;
+                };
+                let conflicting = self.indices(&conflicting);
+                match operations::install_plan(&self.addons, index) {
+                    Ok((install_steps, _)) => {
+                        let title = self.install_title(index);
+                        let mut steps: Vec<Step> = conflicting
+                            .iter()
+                            .map(|&i| operations::step(&self.addons, i, Operation::Uninstall))
+                            .collect();
+                        steps.extend(install_steps);
+                        self.run_steps(title, steps);
+                    }
+                    Err(e) => self.report_plan_error(index, e),
+                }
+            }
+        }
+    }
+
+    fn run_steps(&mut self, title: String, steps: Vec<Step>) {
+        self.queue.push(title, steps);
+    }
+
+    fn show_queue(&mut self, ui: &mut egui::Ui) {
+        let jobs = self.queue.jobs();
+        if jobs.is_empty() {
+            return;
+        }
+
+        egui::CollapsingHeader::new(format!("📋 Очередь операций ({})", jobs.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                for job in &jobs {
+                    ui.horizontal(|ui| {
+                        ui.label(&job.title);
+                        match &job.status {
+                            JobStatus::Queued => {
+                                ui.colored_label(egui::Color32::GRAY, "⏳ в очереди");
+                            }
+                            JobStatus::Running => {
+                                ui.colored_label(egui::Color32::YELLOW, "▶ выполняется");
+                            }
+                            JobStatus::Done => {
+                                ui.colored_label(egui::Color32::GREEN, "✅ готово");
+                            }
+                            JobStatus::Cancelled => {
+                                ui.colored_label(egui::Color32::GRAY, "⏹ отменено");
+                            }
+                            JobStatus::Failed(e) => {
+                                ui.colored_label(egui::Color32::RED, format!("❌ {}", e));
+                            }
+                        }
+                    });
+                }
+
+                if jobs.iter().any(|job| job.status.is_finished())
+                    && ui.button("Очистить завершённые").clicked()
+                {
+                    self.queue.clear_finished();
+                }
+            });
+    }
+
+    fn show_profile_selector(&mut self, ui: &mut egui::Ui) {
+        let active = self.settings.active_index();
+        let mut selected = active;
+
+        ui.add_enabled_ui(!self.is_busy(), |ui| {
+            egui::ComboBox::from_id_salt("profile")
+                .selected_text(
+                    active
+                        .map(|index| self.settings.profiles[index].name.as_str())
+                        .unwrap_or_default(),
+                )
+                .show_ui(ui, |ui| {
+                    for (index, profile) in self.settings.profiles.iter().enumerate() {
+                        ui.selectable_value(&mut selected, Some(index), profile.name.as_str());
+                    }
+                });
         });
+
+        if let (Some(index), true) = (selected, selected != active) {
+            self.select_profile(index);
+        }
+    }
+
+    fn show_log_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("📜 Журнал").show(ui, |ui| {
+            // Новые записи появляются без действий пользователя
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+            let records = log_buffer::records(self.log_level);
+
+            ui.horizontal(|ui| {
+                let levels = [
+                    (LevelFilter::Error, "Ошибки"),
+                    (LevelFilter::Warn, "Предупреждения"),
+                    (LevelFilter::Info, "Все"),
+                ];
+                let selected = levels
+                    .iter()
+                    .find(|(level, _)| *level == self.log_level)
+                    .map_or("", |(_, label)| *label);
+
+                egui::ComboBox::from_id_salt("log_level")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (level, label) in levels {
+                            ui.selectable_value(&mut self.log_level, level, label);
+                        }
+                    });
+
+                if ui.button("📋 Копировать").clicked() {
+                    let text: Vec<String> = records.iter().map(LogRecord::line).collect();
+                    ui.ctx().copy_text(text.join("\n"));
+                }
+            });
+
+            ScrollArea::vertical()
+                .id_salt("log_records")
+                .max_height(200.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for record in &records {
+                        let color = match record.level {
+                            Level::Error => egui::Color32::RED,
+                            Level::Warn => egui::Color32::YELLOW,
+                            _ => egui::Color32::GRAY,
+                        };
+                        ui.label(
+                            egui::RichText::new(record.line())
+                                .monospace()
+                                .small()
+                                .color(color),
+                        );
+                    }
+                });
+        });
+    }
+
+    fn show_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(confirmation) = &self.pending_confirmation else {
+            return;
+        };
+        let mut decision = None;
+
+        egui::Window::new("Подтверждение")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                match confirmation {
+                    Confirmation::Uninstall { names, dependents } => {
+                        ui.label(format!(
+                            "От {} зависят: {}.",
+                            names.join(", "),
+                            dependents.join(", ")
+                        ));
+                        ui.label("После удаления они могут перестать работать. Удалить?");
+                    }
+                    Confirmation::Conflict { name, conflicting } => {
+                        ui.label(format!(
+                            "{} несовместим с: {}.",
+                            name,
+                            conflicting.join(", ")
+                        ));
+                        ui.label("Удалить несовместимые аддоны и продолжить установку?");
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Да").clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button("Отмена").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+
+        if let Some(confirmed) = decision {
+            if let Some(confirmation) = self.pending_confirmation.take() {
+                if confirmed {
+                    self.apply_confirmation(confirmation);
+                }
+            }
+        }
     }
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if !self.initial_size_set {
             ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::Vec2::new(
@@ -140,46 +934,312 @@ impl eframe::App for App {
             self.initial_size_set = true;
         }
 
-        if self.last_nsqc_check.elapsed() >= self.nsqc_check_interval {
-            self.check_nsqc_update();
-            self.last_nsqc_check = Instant::now();
+        if self.offline_since.is_none()
+            && self.last_update_check.elapsed() >= self.update_check_interval
+        {
+            self.check_updates();
+            self.last_update_check = Instant::now();
         }
 
         CentralPanel::default().show(ctx, |ui| {
             egui::TopBottomPanel::top("top_panel").show_inside(ui, |ui| {
                 ui.vertical_centered(|ui| {
-                    if self.game_available {
-                        if ui.button("🚀 Запустить игру").clicked() {
-                            match launch_game() {
-                                Ok(_) => info!("Game launched successfully"),
-                                Err(e) => error!("Failed to launch game: {}", e),
-                            }
+                    ui.horizontal(|ui| {
+                        if !self.settings.profiles.is_empty() {
+                            self.show_profile_selector(ui);
                         }
-                    } else {
+                        if self.game_available && ui.button("🚀 Запустить игру").clicked()
+                        {
+                            self.launch_game();
+                        }
+                    });
+                    if let Some(error) = &self.launch_error {
                         ui.colored_label(
                             egui::Color32::RED,
-                            "❌ Игра не найдена в текущей директории",
+                            format!("❌ Не удалось запустить игру: {}", error),
                         );
                     }
+                    if !self.game_available {
+                        ui.colored_label(
+                            egui::Color32::RED,
+                            format!("❌ Игра не найдена в {}", config::base_dir().display()),
+                        );
+                        #[cfg(not(target_os = "android"))]
+                        if ui
+                            .add_enabled(
+                                !self.is_busy(),
+                                egui::Button::new("📂 Выбрать папку игры"),
+                            )
+                            .clicked()
+                        {
+                            self.pick_game_dir();
+                        }
+                    }
+                    self.show_self_update(ui);
                 });
             });
 
+            egui::TopBottomPanel::bottom("log_panel").show_inside(ui, |ui| {
+                self.show_log_panel(ui);
+            });
+
+            if let Some(saved_at) = self.offline_since {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "⚠ Нет связи, используется сохранённый список от {}",
+                            config::format_timestamp(saved_at)
+                        ),
+                    );
+                    if ui
+                        .add_enabled(!self.is_busy(), egui::Button::new("🔄"))
+                        .clicked()
+                    {
+                        self.reload_manifest();
+                    }
+                });
+            }
+
+            if let Some(error) = self.manifest_error.clone() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("❌ Не удалось загрузить список аддонов: {}", error),
+                    );
+                    if ui.button("🔄").clicked() {
+                        self.reload_manifest();
+                    }
+                });
+            }
+
             ui.heading("Addon Manager");
+            ui.small(format!("Источник: {}", self.manifest_source));
+
+            egui::CollapsingHeader::new("⚙ Настройки").show(ui, |ui| {
+                let busy = self.is_busy();
+
+                ui.label("Профиль:");
+                ui.text_edit_singleline(&mut self.profile_name_input);
+                ui.label("Папка игры:");
+                ui.text_edit_singleline(&mut self.game_dir_input);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("Применить"))
+                        .clicked()
+                    {
+                        self.apply_game_dir();
+                    }
+                    #[cfg(not(target_os = "android"))]
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("📂 Выбрать…"))
+                        .clicked()
+                    {
+                        self.pick_game_dir();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("➕ Новый профиль"))
+                        .clicked()
+                    {
+                        self.add_profile();
+                    }
+                    let can_remove = !busy && !self.settings.profiles.is_empty();
+                    if ui
+                        .add_enabled(can_remove, egui::Button::new("🗑 Удалить профиль"))
+                        .clicked()
+                    {
+                        self.remove_profile();
+                    }
+                });
+                if let Some(error) = &self.game_dir_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                if let Some(index) = self.settings.active_index() {
+                    let launch = &mut self.settings.profiles[index].launch;
+                    ui.label("Аргументы запуска:");
+                    ui.text_edit_singleline(&mut launch.arguments);
+                    ui.label("Команда запуска:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut launch.template)
+                            .hint_text(launcher::DEFAULT_TEMPLATE),
+                    );
+                    #[cfg(unix)]
+                    show_wine_settings(ui, &mut launch.wine);
+                    match launcher::prepare(launch) {
+                        Ok(command) => {
+                            ui.small(format!("Будет запущено: {}", command));
+                        }
+                        Err(e) => {
+                            ui.colored_label(egui::Color32::RED, e.to_string());
+                        }
+                    }
+                }
+                ui.add_space(4.0);
+
+                ui.label("Сервер (realmlist.wtf):");
+                ui.text_edit_singleline(&mut self.realmlist_input);
+                ui.horizontal(|ui| {
+                    if ui.button("Сохранить").clicked() {
+                        self.apply_realmlist(self.realmlist_input.trim().to_string());
+                    }
+                    if let Some(address) = self.realmlist.clone() {
+                        if ui
+                            .button("Из списка аддонов")
+                            .on_hover_text(&address)
+                            .clicked()
+                        {
+                            self.apply_realmlist(address);
+                        }
+                    }
+                });
+                if let Some(index) = self.settings.active_index() {
+                    ui.add_enabled(
+                        self.realmlist.is_some(),
+                        egui::Checkbox::new(
+                            &mut self.settings.profiles[index].apply_realmlist,
+                            "Устанавливать сервер из списка перед запуском",
+                        ),
+                    );
+                }
+                if let Some(error) = &self.realmlist_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                ui.add_space(4.0);
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.game_available, egui::Button::new("🧹 Очистить кэш"))
+                        .on_hover_text("Удаляет Cache/WDB: игра создаст его заново")
+                        .clicked()
+                    {
+                        self.clear_cache();
+                    }
+                    match &self.cache_status {
+                        Some(Ok(freed)) => {
+                            ui.label(format!("Освобождено {}", config::format_size(*freed)));
+                        }
+                        Some(Err(error)) => {
+                            ui.colored_label(egui::Color32::RED, error);
+                        }
+                        None => {}
+                    }
+                });
+                if let Some(index) = self.settings.active_index() {
+                    ui.checkbox(
+                        &mut self.settings.profiles[index].clear_cache,
+                        "Очищать кэш перед запуском",
+                    );
+                }
+                ui.add_space(4.0);
+
+                let parallel_jobs = egui::Slider::new(
+                    &mut self.settings.parallel_jobs,
+                    1..=config::MAX_PARALLEL_JOBS,
+                )
+                .text("одновременных операций");
+                if ui.add(parallel_jobs).changed() {
+                    self.queue.set_limit(self.settings.parallel_jobs);
+                }
+                ui.add_space(4.0);
+
+                ui.label("Список аддонов (URL или путь к файлу):");
+                ui.text_edit_singleline(&mut self.manifest_input);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("Применить"))
+                        .clicked()
+                    {
+                        self.apply_manifest_source();
+                    }
+                    if ui.button("По умолчанию").clicked() {
+                        self.manifest_input = config::DEFAULT_MANIFEST_URL.to_string();
+                    }
+                });
+                ui.add_space(4.0);
+
+                ui.label(format!(
+                    "Обновления программы (версия {}):",
+                    self_update::CURRENT_VERSION
+                ));
+                ui.text_edit_singleline(&mut self.release_feed_input);
+                ui.horizontal(|ui| {
+                    if ui.button("Проверить").clicked() {
+                        self.apply_release_feed();
+                    }
+                    if ui
+                        .add_enabled(
+                            !busy && self_update::has_previous_version(),
+                            egui::Button::new("↩ Вернуть предыдущую версию"),
+                        )
+                        .clicked()
+                    {
+                        self.rollback_self_update(ui.ctx());
+                    }
+                });
+            });
             ui.separator();
 
             let mut indices_to_toggle = Vec::new();
+            let mut indices_to_update = Vec::new();
+            let mut indices_to_retry = Vec::new();
+            let mut indices_to_dismiss = Vec::new();
+            let mut selection_changes = Vec::new();
+            let offline = self.offline_since.is_some();
+
+            ui.horizontal_wrapped(|ui| {
+                let has_updates = self.addons.iter().any(|(_, state)| {
+                    let state = state.lock().unwrap();
+                    state.needs_update && !state.installing
+                });
+                if ui
+                    .add_enabled(
+                        !offline && has_updates,
+                        egui::Button::new("⏫ Обновить все"),
+                    )
+                    .clicked()
+                {
+                    self.update_all();
+                }
+
+                let count = self.selected.len();
+                if ui
+                    .add_enabled(
+                        !offline && count > 0,
+                        egui::Button::new(format!("Установить выбранные ({})", count)),
+                    )
+                    .clicked()
+                {
+                    self.install_selected();
+                }
+                if ui
+                    .add_enabled(
+                        count > 0,
+                        egui::Button::new(format!("Удалить выбранные ({})", count)),
+                    )
+                    .clicked()
+                {
+                    self.remove_selected();
+                }
+            });
+            ui.small("Нажмите на название аддона, чтобы выбрать его");
+            self.show_queue(ui);
+            ui.separator();
 
             ScrollArea::vertical().show(ui, |ui| {
                 for (i, (addon, state)) in self.addons.iter().enumerate() {
                     let state_lock = state.lock().unwrap();
 
                     ui.horizontal(|ui| {
-                        if addon.name == "NSQC" && state_lock.needs_update {
+                        if state_lock.needs_update {
                             ui.colored_label(egui::Color32::YELLOW, "⏫");
                         }
 
-                        let enabled = !state_lock.installing;
                         let mut current_state = state_lock.target_state.unwrap_or(false);
+                        // Без сети можно только удалять аддоны
+                        let enabled = !state_lock.installing && (!offline || current_state);
 
                         let response =
                             ui.add_enabled_ui(enabled, |ui| ui.checkbox(&mut current_state, ""));
@@ -190,14 +1250,80 @@ impl eframe::App for App {
 
                         ui.vertical(|ui| {
                             ui.horizontal(|ui| {
-                                ui.heading(&addon.name);
-                                if addon.name == "NSQC" && state_lock.needs_update {
+                                let selected = self.selected.contains(&addon.name);
+                                let title = egui::RichText::new(&addon.name).heading();
+                                if ui.selectable_label(selected, title).clicked() {
+                                    selection_changes.push(addon.name.clone());
+                                }
+                                if state_lock.needs_update {
                                     ui.colored_label(egui::Color32::GREEN, "(Доступно обновление)");
+                                    let can_update = !state_lock.installing && !offline;
+                                    if ui
+                                        .add_enabled(can_update, egui::Button::new("Обновить"))
+                                        .clicked()
+                                    {
+                                        indices_to_update.push(i);
+                                    }
                                 }
                             });
                             ui.label(&addon.description);
+                            if !addon.depends.is_empty() {
+                                ui.small(format!("Зависит от: {}", addon.depends.join(", ")));
+                            }
+                            let required_by: Vec<&str> = self
+                                .addons
+                                .iter()
+                                .filter(|(other, _)| other.depends.contains(&addon.name))
+                                .map(|(other, _)| other.name.as_str())
+                                .collect();
+                            if !required_by.is_empty() {
+                                ui.small(format!("Нужен для: {}", required_by.join(", ")));
+                            }
+                            if !addon.conflicts.is_empty() {
+                                ui.small(format!("Несовместим с: {}", addon.conflicts.join(", ")));
+                            }
+                            if let Some(error) = &state_lock.error {
+                                ui.colored_label(egui::Color32::RED, format!("❌ {}", error));
+                                ui.horizontal(|ui| {
+                                    // Без сети повторить можно только удаление
+                                    let can_retry = !state_lock.installing
+                                        && match state_lock.last_operation {
+                                            Some(Operation::Install) => !offline,
+                                            Some(Operation::Uninstall) => true,
+                                            None => false,
+                                        };
+                                    if ui
+                                        .add_enabled(can_retry, egui::Button::new("🔄 Повторить"))
+                                        .clicked()
+                                    {
+                                        indices_to_retry.push(i);
+                                    }
+                                    if ui.button("Скрыть").clicked() {
+                                        indices_to_dismiss.push(i);
+                                    }
+                                });
+                            }
+                            if let Some(installed) = &state_lock.installed_version {
+                                let version = match &state_lock.remote_version {
+                                    Some(remote) if remote != installed => {
+                                        format!("Версия: {} → {}", installed, remote)
+                                    }
+                                    _ => format!("Версия: {}", installed),
+                                };
+                                ui.small(version);
+                            }
                             if state_lock.installing {
-                                ui.add(ProgressBar::new(state_lock.progress).show_percentage());
+                                ui.horizontal(|ui| {
+                                    let cancelling = state_lock.cancel.is_cancelled();
+                                    if ui
+                                        .add_enabled(!cancelling, egui::Button::new("⏹ Отмена"))
+                                        .clicked()
+                                    {
+                                        info!("Cancel requested: {}", addon.name);
+                                        state_lock.cancel.cancel();
+                                    }
+                                    show_progress(ui, &state_lock);
+                                });
                             }
                         });
                     });
@@ -205,26 +1331,93 @@ impl eframe::App for App {
                 }
             });
 
+            for name in selection_changes {
+                if !self.selected.remove(&name) {
+                    self.selected.insert(name);
+                }
+            }
             for index in indices_to_toggle {
                 self.toggle_addon(index);
             }
+            for index in indices_to_dismiss {
+                self.dismiss_error(index);
+            }
+            for index in indices_to_retry {
+                self.retry_addon(index);
+            }
+            for index in indices_to_update {
+                self.update_addon(index);
+            }
         });
+
+        self.show_confirmation(ctx);
+
+        if self.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
     }
 }
 
-fn launch_game() -> Result<(), std::io::Error> {
-    let exe_path = config::get_wow_path();
-
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        Command::new(exe_path).creation_flags(0x08000000).spawn()?;
+fn show_progress(ui: &mut egui::Ui, state: &AddonState) {
+    let known_total = state.total.is_some_and(|total| total > 0);
+    let mut details = Vec::new();
+    if known_total {
+        details.push(format!("{:.0}%", state.progress * 100.0));
+    }
+    if state.downloaded > 0 {
+        details.push(match state.total {
+            Some(total) => format!(
+                "{} / {}",
+                config::format_size(state.downloaded),
+                config::format_size(total)
+            ),
+            None => config::format_size(state.downloaded),
+        });
+    }
+    if state.speed > 0.0 {
+        details.push(format!("{}/с", config::format_size(state.speed as u64)));
+    }
+    if let Some(eta) = state.eta() {
+        let secs = eta.as_secs();
+        details.push(format!("~{}:{:02}", secs / 60, secs % 60));
     }
+    let text = details.join(" · ");
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        Command::new(exe_path).spawn()?;
+    // Без Content-Length доля неизвестна, поэтому только счётчик байтов
+    if known_total {
+        ui.add(ProgressBar::new(state.progress).text(text));
+    } else {
+        ui.spinner();
+        ui.small(text);
     }
+}
 
-    Ok(())
+#[cfg(unix)]
+fn show_wine_settings(ui: &mut egui::Ui, wine: &mut config::WineSettings) {
+    ui.label("Wine:");
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut wine.binary).hint_text(launcher::DEFAULT_WINE));
+        // PATH просматривается только при открытом списке
+        egui::ComboBox::from_id_salt("wine_binary")
+            .selected_text("🔍")
+            .show_ui(ui, |ui| {
+                let found = launcher::detect_wine();
+                if found.is_empty() {
+                    ui.label("Wine не найден в PATH");
+                }
+                for path in found {
+                    let path = path.display().to_string();
+                    if ui
+                        .selectable_label(wine.binary == path, path.as_str())
+                        .clicked()
+                    {
+                        wine.binary = path;
+                    }
+                }
+            });
+    });
+    ui.label("WINEPREFIX:");
+    ui.add(egui::TextEdit::singleline(&mut wine.prefix).hint_text("по умолчанию ~/.wine"));
+    ui.label("Переменные окружения Wine:");
+    ui.add(egui::TextEdit::singleline(&mut wine.env).hint_text("DXVK_HUD=fps WINEDEBUG=-all"));
 }
@@ -0,0 +1,541 @@
+use crate::config;
+use crate::modules::operations::{self, AddonEntry, Operation, Step};
+use crate::modules::{addon_manager, game_cache, launcher, realmlist};
+use anyhow::Result;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use ureq::Agent;
+
+pub const USAGE: &str = "\
+Использование: nightwatch-updater [ПАРАМЕТРЫ] [КОМАНДА]
+
+Без команды открывается окно программы.
+
+Команды:
+  list                 список аддонов
+  status               состояние и версии аддонов
+  install <имя>        установить аддон вместе с зависимостями
+  uninstall <имя>      удалить аддон
+  update <имя>         обновить аддон
+  update --all         обновить все установленные аддоны
+  launch               запустить игру
+  help                 эта справка
+
+Параметры:
+  --manifest <url|путь>  источник списка аддонов
+  --profile <имя>        профиль игры вместо выбранного в окне программы
+  --game-dir <путь>      папка игры вместо папки профиля
+  --json                 вывод в формате JSON (по событию на строку для операций)
+  --force                удалять несовместимые и нужные другим аддоны без отказа";
+
+pub enum Command {
+    List,
+    Status,
+    Install(String),
+    Uninstall(String),
+    Update(Option<String>),
+    Launch,
+    Help,
+}
+
+#[derive(Default)]
+pub struct Args {
+    pub manifest: Option<String>,
+    pub profile: Option<String>,
+    pub game_dir: Option<PathBuf>,
+    pub force: bool,
+    pub json: bool,
+    pub command: Option<Command>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut positional = Vec::new();
+        let mut all = false;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--manifest=") {
+                parsed.manifest = Some(value.to_string());
+                continue;
+            }
+            if let Some(value) = arg.strip_prefix("--profile=") {
+                parsed.profile = Some(value.to_string());
+                continue;
+            }
+            if let Some(value) = arg.strip_prefix("--game-dir=") {
+                parsed.game_dir = Some(PathBuf::from(value));
+                continue;
+            }
+
+            match arg.as_str() {
+                "--manifest" => {
+                    parsed.manifest = Some(args.next().ok_or("--manifest requires a value")?);
+                }
+                "--profile" => {
+                    parsed.profile = Some(args.next().ok_or("--profile requires a value")?);
+                }
+                "--game-dir" => {
+                    let value = args.next().ok_or("--game-dir requires a value")?;
+                    parsed.game_dir = Some(PathBuf::from(value));
+                }
+                "--all" => all = true,
+                "--force" => parsed.force = true,
+                "--json" => parsed.json = true,
+                "-h" | "--help" => positional.push("help".to_string()),
+                _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let words: Vec<&str> = positional.iter().map(String::as_str).collect();
+        parsed.command = match words.as_slice() {
+            [] => None,
+            ["list"] => Some(Command::List),
+            ["status"] => Some(Command::Status),
+            ["install", name] => Some(Command::Install(name.to_string())),
+            ["uninstall", name] => Some(Command::Uninstall(name.to_string())),
+            ["update"] if all => Some(Command::Update(None)),
+            ["update", name] if !all => Some(Command::Update(Some(name.to_string()))),
+            ["launch"] => Some(Command::Launch),
+            ["help", ..] => Some(Command::Help),
+            _ => return Err(format!("Unknown command: {}", words.join(" "))),
+        };
+
+        Ok(parsed)
+    }
+}
+
+#[derive(Serialize)]
+struct AddonStatus<'a> {
+    name: &'a str,
+    description: &'a str,
+    installed: bool,
+    installed_version: Option<String>,
+    remote_version: Option<String>,
+    needs_update: bool,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Started {
+        addon: &'a str,
+        operation: &'static str,
+    },
+    Progress {
+        addon: &'a str,
+        operation: &'static str,
+        progress: f32,
+        downloaded: u64,
+        total: Option<u64>,
+        bytes_per_second: u64,
+    },
+    Result {
+        addon: &'a str,
+        operation: &'static str,
+        success: bool,
+        error: Option<&'a str>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+fn emit(event: &Event) {
+    match serde_json::to_string(event) {
+        Ok(line) => println!("{}", line),
+        Err(e) => error!("Failed to serialize event: {}", e),
+    }
+}
+
+pub fn run(args: &Args, command: Command) -> i32 {
+    match execute(args, command) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            error!("Command failed: {:?}", e);
+            if args.json {
+                emit(&Event::Error {
+                    message: e.to_string(),
+                });
+            } else {
+                eprintln!("❌ {}", e);
+            }
+            1
+        }
+    }
+}
+
+fn execute(args: &Args, command: Command) -> Result<bool> {
+    if let Command::Help = command {
+        println!("{}", USAGE);
+        return Ok(true);
+    }
+
+    let settings = config::load_saved_settings();
+    let profile = match &args.profile {
+        Some(name) => Some(
+            settings
+                .profile(name)
+                .ok_or_else(|| anyhow::anyhow!("Профиль не найден: {}", name))?,
+        ),
+        None => settings.active(),
+    };
+    if let Some(profile) = profile {
+        info!("Using profile: {}", profile.name);
+    }
+
+    let game_dir = config::resolve_game_dir(
+        args.game_dir.as_deref(),
+        profile.map(|profile| profile.game_dir.as_path()),
+    );
+    if let Some(dir) = game_dir {
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("Папка игры не найдена: {}", dir.display()));
+        }
+        config::set_base_dir(Some(dir));
+    }
+    info!("Using game directory: {}", config::base_dir().display());
+
+    let client = config::http_client();
+    let source = config::resolve_manifest_source(
+        args.manifest.as_deref(),
+        settings.manifest_source.as_deref(),
+    );
+    info!("Using manifest source: {}", source);
+
+    if let Command::Launch = command {
+        let profile = profile.cloned().unwrap_or_default();
+        // Без списка аддонов игра всё равно запускается, с прежним realmlist
+        if profile.apply_realmlist {
+            match config::load_addons_config_blocking(&client, &source) {
+                Ok(manifest) => {
+                    if let Some(address) = manifest.realmlist {
+                        realmlist::apply(&address)?;
+                    }
+                }
+                Err(e) => {
+                    warn!("Realmlist not applied, manifest unavailable: {}", e);
+                    eprintln!("⚠ Нет списка аддонов, realmlist не обновлён");
+                }
+            }
+        }
+        if profile.clear_cache {
+            let freed = game_cache::clear()?;
+            if !args.json {
+                println!("🧹 Кэш очищен: {}", config::format_size(freed));
+            }
+        }
+        launcher::launch_game(&profile.launch)?;
+        info!("Game launched successfully");
+        if !args.json {
+            println!("🚀 Игра запущена");
+        }
+        return Ok(true);
+    }
+
+    let manifest = config::load_addons_config_blocking(&client, &source)?;
+    if let Some(saved_at) = manifest.cached_at {
+        eprintln!(
+            "⚠ Нет связи, используется сохранённый список от {}",
+            config::format_timestamp(saved_at)
+        );
+    }
+
+    let session = Session {
+        client,
+        entries: operations::build_entries(manifest.addons),
+        force: args.force,
+        json: args.json,
+    };
+
+    match command {
+        Command::List => session.list(),
+        Command::Status => session.status(),
+        Command::Install(name) => session.install(&name, session.force),
+        Command::Uninstall(name) => session.uninstall(&name),
+        Command::Update(Some(name)) => session.update_one(&name),
+        Command::Update(None) => session.update_all(),
+        Command::Launch | Command::Help => unreachable!(),
+    }
+}
+
+struct Session {
+    client: Agent,
+    entries: Vec<AddonEntry>,
+    force: bool,
+    json: bool,
+}
+
+impl Session {
+    fn list(&self) -> Result<bool> {
+        if self.json {
+            let statuses: Vec<_> = (0..self.entries.len())
+                .map(|index| self.addon_status(index))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+            return Ok(true);
+        }
+
+        for (index, (addon, _)) in self.entries.iter().enumerate() {
+            let mark = if operations::is_installed(&self.entries, index) {
+                "x"
+            } else {
+                " "
+            };
+            println!("[{}] {} — {}", mark, addon.name, addon.description);
+        }
+        Ok(true)
+    }
+
+    fn status(&self) -> Result<bool> {
+        let mut success = true;
+
+        for (addon, state) in &self.entries {
+            if let Err(e) = addon_manager::refresh_update_state(&self.client, addon, state) {
+                eprintln!("⚠ {}: не удалось проверить версию: {}", addon.name, e);
+                success = false;
+            }
+        }
+
+        let statuses: Vec<_> = (0..self.entries.len())
+            .map(|index| self.addon_status(index))
+            .collect();
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+            return Ok(success);
+        }
+
+        for status in statuses {
+            let mut line = format!(
+                "{}: {}",
+                status.name,
+                if status.installed {
+                    "установлен"
+                } else {
+                    "не установлен"
+                }
+            );
+            if let Some(version) = &status.installed_version {
+                line.push_str(&format!(", версия {}", version));
+            }
+            if let (true, Some(remote)) = (status.needs_update, &status.remote_version) {
+                line.push_str(&format!(", доступна {}", remote));
+            }
+            println!("{}", line);
+        }
+
+        Ok(success)
+    }
+
+    fn install(&self, name: &str, force: bool) -> Result<bool> {
+        let index = self.find(name)?;
+        let (install_steps, conflicting) = operations::install_plan(&self.entries, index)?;
+
+        if !conflicting.is_empty() && !force {
+            let names: Vec<&str> = conflicting
+                .iter()
+                .map(|&i| self.entries[i].0.name.as_str())
+                .collect();
+            return Err(anyhow::anyhow!(
+                "{} несовместим с: {} (используйте --force, чтобы удалить их)",
+                name,
+                names.join(", ")
+            ));
+        }
+
+        let mut steps: Vec<Step> = conflicting
+            .iter()
+            .map(|&i| operations::step(&self.entries, i, Operation::Uninstall))
+            .collect();
+        steps.extend(install_steps);
+
+        Ok(self.run_steps(&steps))
+    }
+
+    fn uninstall(&self, name: &str) -> Result<bool> {
+        let index = self.find(name)?;
+        let dependents = operations::installed_dependents(&self.entries, index);
+
+        if !dependents.is_empty() && !self.force {
+            return Err(anyhow::anyhow!(
+                "От {} зависят: {} (используйте --force, чтобы всё равно удалить)",
+                name,
+                dependents.join(", ")
+            ));
+        }
+
+        let steps = vec![operations::step(&self.entries, index, Operation::Uninstall)];
+        Ok(self.run_steps(&steps))
+    }
+
+    fn update_one(&self, name: &str) -> Result<bool> {
+        let index = self.find(name)?;
+        if !operations::is_installed(&self.entries, index) {
+            return Err(anyhow::anyhow!("{} не установлен", name));
+        }
+
+        self.install(name, false)
+    }
+
+    fn update_all(&self) -> Result<bool> {
+        let mut success = true;
+        let mut updated = 0;
+
+        for (index, (addon, state)) in self.entries.iter().enumerate() {
+            if !operations::is_installed(&self.entries, index) {
+                continue;
+            }
+
+            if let Err(e) = addon_manager::refresh_update_state(&self.client, addon, state) {
+                eprintln!("⚠ {}: не удалось проверить версию: {}", addon.name, e);
+                success = false;
+                continue;
+            }
+
+            if !state.lock().unwrap().needs_update {
+                continue;
+            }
+
+            updated += 1;
+            match self.install(&addon.name, false) {
+                Ok(result) => success &= result,
+                Err(e) => {
+                    if self.json {
+                        emit(&Event::Error {
+                            message: format!("{}: {}", addon.name, e),
+                        });
+                    } else {
+                        eprintln!("❌ {}: {}", addon.name, e);
+                    }
+                    success = false;
+                }
+            }
+        }
+
+        if updated == 0 && !self.json {
+            println!("Все аддоны в актуальном состоянии");
+        }
+
+        Ok(success)
+    }
+
+    fn run_steps(&self, steps: &[Step]) -> bool {
+        let success = if self.json {
+            self.run_steps_with_events(steps)
+        } else {
+            operations::run_sequence(&self.client, steps).is_ok()
+        };
+
+        for (addon, state, operation) in steps {
+            let state = state.lock().unwrap();
+            if self.json {
+                emit(&Event::Result {
+                    addon: &addon.name,
+                    operation: operation.as_str(),
+                    success: state.error.is_none(),
+                    error: state.error.as_deref(),
+                });
+                continue;
+            }
+
+            match (&state.error, operation) {
+                (Some(e), _) => eprintln!("❌ {}: {}", addon.name, e),
+                (None, Operation::Install) => println!("✅ {}: установлен", addon.name),
+                (None, Operation::Uninstall) => println!("✅ {}: удалён", addon.name),
+            }
+        }
+
+        success
+    }
+
+    // Операция идёт в отдельном потоке, а этот опрашивает состояние и пишет события
+    fn run_steps_with_events(&self, steps: &[Step]) -> bool {
+        let started: Vec<AtomicBool> = steps.iter().map(|_| AtomicBool::new(false)).collect();
+
+        std::thread::scope(|scope| {
+            let worker = scope.spawn(|| {
+                operations::run_sequence_with(&self.client, steps, |position| {
+                    let (addon, _, operation) = &steps[position];
+                    emit(&Event::Started {
+                        addon: &addon.name,
+                        operation: operation.as_str(),
+                    });
+                    started[position].store(true, Ordering::SeqCst);
+                })
+                .is_ok()
+            });
+            let mut reported: Vec<Option<(f32, u64)>> = vec![None; steps.len()];
+
+            while !worker.is_finished() {
+                for (position, (addon, state, operation)) in steps.iter().enumerate() {
+                    if !started[position].load(Ordering::SeqCst) {
+                        continue;
+                    }
+
+                    let (progress, downloaded, total, speed) = {
+                        let state = state.lock().unwrap();
+                        if !state.installing {
+                            continue;
+                        }
+                        (state.progress, state.downloaded, state.total, state.speed)
+                    };
+
+                    // Без известного размера прогресс — это только счётчик байтов
+                    let changed = match reported[position] {
+                        None => true,
+                        Some((last_progress, _)) if total.is_some() => {
+                            (progress - last_progress).abs() >= 0.01
+                        }
+                        Some((_, last_downloaded)) => downloaded != last_downloaded,
+                    };
+
+                    if changed {
+                        reported[position] = Some((progress, downloaded));
+                        emit(&Event::Progress {
+                            addon: &addon.name,
+                            operation: operation.as_str(),
+                            progress,
+                            downloaded,
+                            total,
+                            bytes_per_second: speed as u64,
+                        });
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(250));
+            }
+
+            worker.join().unwrap_or(false)
+        })
+    }
+
+    fn addon_status(&self, index: usize) -> AddonStatus<'_> {
+        let installed = operations::is_installed(&self.entries, index);
+        let (addon, state) = &self.entries[index];
+        let state = state.lock().unwrap();
+
+        AddonStatus {
+            name: &addon.name,
+            description: &addon.description,
+            installed,
+            installed_version: state.installed_version.clone(),
+            remote_version: state.remote_version.clone(),
+            needs_update: state.needs_update,
+        }
+    }
+
+    fn find(&self, name: &str) -> Result<usize> {
+        operations::index_of(&self.entries, name)
+            .ok_or_else(|| anyhow::anyhow!("Аддон не найден: {}", name))
+    }
+}
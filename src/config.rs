@@ -1,16 +1,215 @@
 use crate::app::Addon;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use indexmap::IndexMap;
-use serde::{de, Deserialize};
-use std::path::PathBuf;
+use log::warn;
+use serde::{de, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ureq::Agent;
 
+pub const APP_NAME: &str = "Night Watch Updater";
+pub const DEFAULT_MANIFEST_URL: &str =
+    "https://raw.githubusercontent.com/Vladgobelen/NSQCu/refs/heads/main/addons.json";
+pub const MANIFEST_ENV_VAR: &str = "NIGHTWATCH_MANIFEST";
+// release.json собирается в .github/workflows/release.yml и прикладывается к выпуску
+pub const DEFAULT_RELEASE_FEED_URL: &str =
+    "https://github.com/Vladgobelen/NSQCuR/releases/latest/download/release.json";
+pub const DEFAULT_PROFILE_NAME: &str = "Основной";
+pub const DEFAULT_PARALLEL_JOBS: usize = 2;
+pub const MAX_PARALLEL_JOBS: usize = 8;
+
+// Источники версий для записей манифеста, которые ещё не объявляют их сами
+const KNOWN_VERSIONS: [(&str, &str, &str); 1] = [(
+    "NSQC",
+    "https://raw.githubusercontent.com/Vladgobelen/NSQC/main/vers",
+    "Interface/AddOns/NSQC/vers",
+)];
+
+static GAME_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WineSettings {
+    pub binary: String,
+    pub prefix: String,
+    pub env: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchSettings {
+    pub arguments: String,
+    pub template: String,
+    pub wine: WineSettings,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameProfile {
+    pub name: String,
+    pub game_dir: PathBuf,
+    pub launch: LaunchSettings,
+    // Записывать realmlist из списка аддонов перед каждым запуском
+    pub apply_realmlist: bool,
+    pub clear_cache: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub manifest_source: Option<String>,
+    // Папка игры из версий без профилей, переносится в первый профиль
+    #[serde(skip_serializing)]
+    pub game_dir: Option<PathBuf>,
+    pub profiles: Vec<GameProfile>,
+    pub active_profile: Option<String>,
+    pub parallel_jobs: usize,
+    pub release_feed: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            manifest_source: None,
+            game_dir: None,
+            profiles: Vec::new(),
+            active_profile: None,
+            parallel_jobs: DEFAULT_PARALLEL_JOBS,
+            release_feed: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn migrate(&mut self) {
+        if let Some(game_dir) = self.game_dir.take() {
+            if self.profiles.is_empty() {
+                self.profiles.push(GameProfile {
+                    name: DEFAULT_PROFILE_NAME.to_string(),
+                    game_dir,
+                    ..Default::default()
+                });
+                self.active_profile = Some(DEFAULT_PROFILE_NAME.to_string());
+            }
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&GameProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn active_index(&self) -> Option<usize> {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.iter().position(|p| &p.name == name))
+            .or((!self.profiles.is_empty()).then_some(0))
+    }
+
+    pub fn active(&self) -> Option<&GameProfile> {
+        self.active_index().map(|index| &self.profiles[index])
+    }
+
+    pub fn release_feed(&self) -> &str {
+        self.release_feed
+            .as_deref()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or(DEFAULT_RELEASE_FEED_URL)
+    }
+}
+
+// Настройки окна хранятся в eframe; консольный режим читает тот же файл
+pub fn load_saved_settings() -> Settings {
+    let Some(path) = eframe::storage_dir(APP_NAME).map(|dir| dir.join("app.ron")) else {
+        return Settings::default();
+    };
+
+    let values: HashMap<String, String> = fs::read_to_string(path)
+        .ok()
+        .and_then(|text| ron::from_str(&text).ok())
+        .unwrap_or_default();
+
+    let mut settings: Settings = values
+        .get(eframe::APP_KEY)
+        .and_then(|value| ron::from_str(value).ok())
+        .unwrap_or_default();
+    settings.migrate();
+    settings
+}
+
+pub fn http_client() -> Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(30))
+        .build()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ManifestSource {
+    Url(String),
+    File(PathBuf),
+}
+
+impl ManifestSource {
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.starts_with("http://") || value.starts_with("https://") {
+            Self::Url(value.to_string())
+        } else {
+            Self::File(PathBuf::from(
+                value.strip_prefix("file://").unwrap_or(value),
+            ))
+        }
+    }
+}
+
+impl Default for ManifestSource {
+    fn default() -> Self {
+        Self::Url(DEFAULT_MANIFEST_URL.to_string())
+    }
+}
+
+impl fmt::Display for ManifestSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Url(url) => write!(f, "{}", url),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// Приоритет: флаг командной строки, переменная окружения, сохранённая настройка
+pub fn resolve_manifest_source(cli: Option<&str>, saved: Option<&str>) -> ManifestSource {
+    [
+        cli.map(str::to_string),
+        std::env::var(MANIFEST_ENV_VAR).ok(),
+        saved.map(str::to_string),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|value| !value.trim().is_empty())
+    .map(|value| ManifestSource::parse(&value))
+    .unwrap_or_default()
+}
+
 #[derive(Deserialize)]
 struct AddonConfig {
     link: String,
     description: String,
     #[serde(deserialize_with = "normalize_path")]
     target_path: String,
+    #[serde(default)]
+    version_url: Option<String>,
+    #[serde(default, deserialize_with = "normalize_optional_path")]
+    version_file: Option<String>,
+    #[serde(default)]
+    sha256: Option<String>,
+    #[serde(default)]
+    depends: Vec<String>,
+    #[serde(default)]
+    conflicts: Vec<String>,
 }
 
 fn normalize_path<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -21,33 +220,84 @@ where
     Ok(path.replace("/", std::path::MAIN_SEPARATOR.to_string().as_str()))
 }
 
-pub fn load_addons_config_blocking(client: &Agent) -> Result<IndexMap<String, Addon>> {
-    let response = client
-        .get("https://raw.githubusercontent.com/Vladgobelen/NSQCu/refs/heads/main/addons.json")
-        .set("User-Agent", "NightWatchUpdater/1.0")
-        .call()?;
+fn normalize_optional_path<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let path = Option::<String>::deserialize(deserializer)?;
+    Ok(path.map(|path| path.replace("/", std::path::MAIN_SEPARATOR.to_string().as_str())))
+}
 
-    if response.status() != 200 {
-        return Err(anyhow::anyhow!(
-            "HTTP Error: {} - {}",
-            response.status(),
-            response.into_string()?
-        ));
-    }
+pub struct Manifest {
+    pub addons: IndexMap<String, Addon>,
+    pub realmlist: Option<String>,
+    pub cached_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestCache {
+    source: String,
+    saved_at: u64,
+    manifest: String,
+}
+
+pub fn load_addons_config_blocking(client: &Agent, source: &ManifestSource) -> Result<Manifest> {
+    let url = match source {
+        ManifestSource::Url(url) => url,
+        ManifestSource::File(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+            return parse_manifest(&text);
+        }
+    };
+
+    let fetched = fetch_manifest(client, url)
+        .and_then(|text| parse_manifest(&text).map(|manifest| (text, manifest)));
 
-    let text = response.into_string()?;
+    match fetched {
+        Ok((text, manifest)) => {
+            if let Err(e) = save_manifest_cache(source, &text) {
+                warn!("Failed to cache manifest: {}", e);
+            }
+            Ok(manifest)
+        }
+        Err(e) => {
+            warn!("Manifest fetch failed, trying cache: {}", e);
+            let cache = load_manifest_cache(source).map_err(|cache_error| {
+                anyhow::anyhow!("{} (cache unavailable: {})", e, cache_error)
+            })?;
+            Ok(Manifest {
+                cached_at: Some(cache.saved_at),
+                ..parse_manifest(&cache.manifest)?
+            })
+        }
+    }
+}
 
+fn parse_manifest(text: &str) -> Result<Manifest> {
     #[derive(Deserialize)]
     struct Config {
         addons: IndexMap<String, AddonConfig>,
+        #[serde(default)]
+        realmlist: Option<String>,
     }
 
-    let config: Config = serde_json::from_str(&text)?;
+    let config: Config = serde_json::from_str(text)?;
 
     let addons = config
         .addons
         .into_iter()
-        .map(|(name, config)| {
+        .map(|(name, mut config)| {
+            if config.version_url.is_none() && config.version_file.is_none() {
+                if let Some((_, url, file)) =
+                    KNOWN_VERSIONS.iter().find(|(known, ..)| *known == name)
+                {
+                    config.version_url = Some(url.to_string());
+                    config.version_file =
+                        Some(file.replace('/', std::path::MAIN_SEPARATOR.to_string().as_str()));
+                }
+            }
+
             (
                 name.clone(),
                 Addon {
@@ -55,18 +305,77 @@ pub fn load_addons_config_blocking(client: &Agent) -> Result<IndexMap<String, Ad
                     link: config.link,
                     description: config.description,
                     target_path: config.target_path,
+                    version_url: config.version_url,
+                    version_file: config.version_file,
+                    sha256: config.sha256,
+                    depends: config.depends,
+                    conflicts: config.conflicts,
                 },
             )
         })
         .collect();
 
-    Ok(addons)
+    Ok(Manifest {
+        addons,
+        realmlist: config
+            .realmlist
+            .map(|realmlist| realmlist.trim().to_string())
+            .filter(|realmlist| !realmlist.is_empty()),
+        cached_at: None,
+    })
+}
+
+fn manifest_cache_path() -> PathBuf {
+    data_dir().join("manifest_cache.json")
+}
+
+fn save_manifest_cache(source: &ManifestSource, text: &str) -> Result<()> {
+    let cache = ManifestCache {
+        source: source.to_string(),
+        saved_at: unix_now(),
+        manifest: text.to_string(),
+    };
+
+    fs::create_dir_all(data_dir())?;
+    fs::write(manifest_cache_path(), serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+fn load_manifest_cache(source: &ManifestSource) -> Result<ManifestCache> {
+    let text = fs::read_to_string(manifest_cache_path())?;
+    let cache: ManifestCache = serde_json::from_str(&text)?;
+
+    if cache.source != source.to_string() {
+        return Err(anyhow::anyhow!(
+            "cached manifest belongs to another source: {}",
+            cache.source
+        ));
+    }
+
+    Ok(cache)
+}
+
+fn fetch_manifest(client: &Agent, url: &str) -> Result<String> {
+    let response = client
+        .get(url)
+        .set("User-Agent", "NightWatchUpdater/1.0")
+        .call()?;
+
+    if response.status() != 200 {
+        return Err(anyhow::anyhow!(
+            "HTTP Error: {} - {}",
+            response.status(),
+            response.into_string()?
+        ));
+    }
+
+    Ok(response.into_string()?)
 }
 
-pub fn check_game_directory() -> Result<()> {
-    let wow_exe = base_dir().join("Wow.exe");
+pub fn check_game_directory(dir: &Path) -> Result<()> {
+    let wow_exe = dir.join("Wow.exe");
     if !wow_exe.exists() {
-        return Err(anyhow::anyhow!("Game not found in current directory"));
+        return Err(anyhow::anyhow!("Game not found in {}", dir.display()));
     }
     Ok(())
 }
@@ -75,6 +384,75 @@ pub fn get_wow_path() -> PathBuf {
     base_dir().join("Wow.exe")
 }
 
+// Приоритет: флаг командной строки, папка профиля, текущая директория
+pub fn resolve_game_dir(cli: Option<&Path>, saved: Option<&Path>) -> Option<PathBuf> {
+    cli.or(saved).map(Path::to_path_buf)
+}
+
+pub fn set_base_dir(dir: Option<PathBuf>) {
+    *GAME_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
 pub fn base_dir() -> PathBuf {
-    std::env::current_dir().expect("Failed to get current directory")
+    GAME_DIR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"))
+}
+
+pub fn data_dir() -> PathBuf {
+    base_dir().join("NightWatchUpdater")
+}
+
+pub fn downloads_dir() -> PathBuf {
+    data_dir().join("downloads")
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["Б", "КБ", "МБ", "ГБ"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn format_timestamp(secs: u64) -> String {
+    // Перевод дней с начала эпохи в календарную дату (алгоритм civil_from_days)
+    let days = (secs / 86_400) as i64;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let rem = secs % 86_400;
+    format!(
+        "{:02}.{:02}.{} {:02}:{:02} UTC",
+        day,
+        month,
+        year,
+        rem / 3_600,
+        rem % 3_600 / 60
+    )
 }
@@ -2,11 +2,14 @@
 #![cfg_attr(target_os = "android", no_main)]
 
 mod app;
+mod cli;
 mod config;
 mod modules;
 
 use app::App;
 use egui::IconData;
+use modules::log_buffer::RingLogger;
+use simplelog::SharedLogger;
 
 #[cfg(target_os = "android")]
 use winit::platform::android::EventLoopBuilderExtAndroid;
@@ -18,12 +21,16 @@ fn android_main(app: android_activity::AndroidApp) {
     use winit::event_loop::EventLoopBuilder;
 
     std::env::set_var("RUST_BACKTRACE", "full");
-    simplelog::WriteLogger::init(
-        simplelog::LevelFilter::Info,
-        simplelog::Config::default(),
-        std::fs::File::create("/sdcard/updater.log").unwrap(),
-    )
-    .unwrap();
+    let mut loggers: Vec<Box<dyn SharedLogger>> =
+        vec![RingLogger::new(simplelog::LevelFilter::Info)];
+    if let Ok(file) = std::fs::File::create("/sdcard/updater.log") {
+        loggers.push(simplelog::WriteLogger::new(
+            simplelog::LevelFilter::Info,
+            simplelog::Config::default(),
+            file,
+        ));
+    }
+    let _ = simplelog::CombinedLogger::init(loggers);
 
     let options = eframe::NativeOptions {
         renderer: Renderer::Wgpu,
@@ -38,7 +45,7 @@ fn android_main(app: android_activity::AndroidApp) {
         options,
         Box::new(|cc| {
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Box::new(App::new(cc))
+            Box::new(App::new(cc, cli::Args::default()))
         }),
     )
     .unwrap();
@@ -46,12 +53,20 @@ fn android_main(app: android_activity::AndroidApp) {
 
 #[cfg(not(target_os = "android"))]
 fn main() -> eframe::Result<()> {
-    simplelog::CombinedLogger::init(vec![simplelog::WriteLogger::new(
-        simplelog::LevelFilter::Info,
-        simplelog::Config::default(),
-        std::fs::File::create("updater.log").unwrap(),
-    )])
-    .unwrap();
+    let mut args = match cli::Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            attach_console();
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_logging(&args);
+
+    if let Some(command) = args.command.take() {
+        attach_console();
+        std::process::exit(cli::run(&args, command));
+    }
 
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
@@ -62,15 +77,69 @@ fn main() -> eframe::Result<()> {
     };
 
     eframe::run_native(
-        "Night Watch Updater",
+        config::APP_NAME,
         options,
         Box::new(|cc| {
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Ok(Box::new(App::new(cc)))
+            Ok(Box::new(App::new(cc, args)))
         }),
     )
 }
 
+// Журнал пишется в папку игры из аргументов или профиля, а если туда нельзя —
+// во временную папку. Без файла остаётся журнал в окне программы.
+#[cfg(not(target_os = "android"))]
+fn init_logging(args: &cli::Args) {
+    let settings = config::load_saved_settings();
+    let profile = match &args.profile {
+        Some(name) => settings.profile(name),
+        None => settings.active(),
+    };
+    let game_dir = config::resolve_game_dir(
+        args.game_dir.as_deref(),
+        profile.map(|profile| profile.game_dir.as_path()),
+    )
+    .or_else(|| std::env::current_dir().ok());
+
+    let log_file = game_dir
+        .map(|dir| dir.join("updater.log"))
+        .into_iter()
+        .chain(std::iter::once(
+            std::env::temp_dir().join("nightwatch-updater.log"),
+        ))
+        .find_map(|path| std::fs::File::create(&path).ok().map(|file| (path, file)));
+
+    let mut loggers: Vec<Box<dyn SharedLogger>> =
+        vec![RingLogger::new(simplelog::LevelFilter::Info)];
+    let mut log_path = None;
+    if let Some((path, file)) = log_file {
+        loggers.push(simplelog::WriteLogger::new(
+            simplelog::LevelFilter::Info,
+            simplelog::Config::default(),
+            file,
+        ));
+        log_path = Some(path);
+    }
+    let _ = simplelog::CombinedLogger::init(loggers);
+
+    match log_path {
+        Some(path) => log::info!("Log file: {}", path.display()),
+        None => log::warn!("Log file could not be created, logging to the app only"),
+    }
+}
+
+// В релизной сборке под Windows у процесса нет своей консоли
+#[cfg(windows)]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
 fn load_icon() -> Option<IconData> {
     let icon_bytes = include_bytes!("../resources/emblem.ico");
     let image = image::load_from_memory(icon_bytes).ok()?.to_rgba8();
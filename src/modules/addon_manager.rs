@@ -1,21 +1,24 @@
 use crate::app::{Addon, AddonState};
 use crate::config;
+use crate::modules::cancellation::CancellationToken;
+use crate::modules::ledger::{self, LedgerEntry};
+use crate::modules::{archive, downloader, staging};
 use anyhow::{Context, Result};
-use fs_extra::dir::CopyOptions as DirCopyOptions;
 use log::{error, info, warn};
 use std::{
+    ffi::OsString,
     fs,
-    fs::File,
-    io::{Read, Write},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    time::Duration,
 };
 use tempfile::tempdir;
 use ureq::Agent;
-use zip_extensions::zip_extract;
 
 pub fn check_addon_installed(addon: &Addon) -> bool {
+    if let Some(entry) = ledger::entry(&addon.name) {
+        return entry.is_installed();
+    }
+
     let target_dir = config::base_dir().join(&addon.target_path);
     let entries = match fs::read_dir(target_dir) {
         Ok(e) => e,
@@ -24,178 +27,254 @@ pub fn check_addon_installed(addon: &Addon) -> bool {
 
     entries.filter_map(|e| e.ok()).any(|entry| {
         let name = entry.file_name().to_string_lossy().into_owned();
-        name.starts_with(&addon.name) || name.contains(&addon.name)
+        !staging::is_staging_artifact(&name)
+            && (name.starts_with(&addon.name) || name.contains(&addon.name))
     })
 }
 
-pub fn check_nsqc_update(client: &Agent) -> Result<bool> {
+#[derive(Clone, Debug, Default)]
+pub struct VersionInfo {
+    pub installed: Option<String>,
+    pub remote: Option<String>,
+    // Без локального файла версии установленная версия неизвестна
+    pub tracked: bool,
+}
+
+impl VersionInfo {
+    pub fn needs_update(&self) -> bool {
+        match (&self.installed, &self.remote) {
+            (Some(installed), Some(remote)) => installed != remote,
+            (None, Some(_)) => self.tracked,
+            _ => false,
+        }
+    }
+}
+
+pub fn installed_version(addon: &Addon) -> Option<String> {
+    let version_file = addon.version_file.as_ref()?;
+    let version = fs::read_to_string(config::base_dir().join(version_file)).ok()?;
+    Some(version.trim().to_string())
+}
+
+pub fn remote_version(client: &Agent, addon: &Addon) -> Result<Option<String>> {
+    let Some(url) = &addon.version_url else {
+        return Ok(None);
+    };
+
     let response = client
-        .get("https://raw.githubusercontent.com/Vladgobelen/NSQC/main/vers")
+        .get(url)
+        .set("User-Agent", "NightWatchUpdater/1.0")
         .call()?;
 
-    let remote_version = response.into_string()?;
+    Ok(Some(response.into_string()?.trim().to_string()))
+}
 
-    let local_path = config::base_dir()
-        .join("Interface")
-        .join("AddOns")
-        .join("NSQC")
-        .join("vers");
+pub fn check_version(client: &Agent, addon: &Addon) -> Result<VersionInfo> {
+    Ok(VersionInfo {
+        remote: remote_version(client, addon)?,
+        installed: installed_version(addon),
+        tracked: addon.version_file.is_some(),
+    })
+}
 
-    if !local_path.exists() {
-        return Ok(true);
+pub fn refresh_update_state(
+    client: &Agent,
+    addon: &Addon,
+    state: &Arc<Mutex<AddonState>>,
+) -> Result<()> {
+    if addon.version_url.is_none() {
+        return Ok(());
     }
 
-    let local_version = fs::read_to_string(local_path)?;
-    Ok(remote_version.trim() != local_version.trim())
+    let version = check_version(client, addon)?;
+    let installed = check_addon_installed(addon);
+
+    let mut state = state.lock().unwrap();
+    state.needs_update = installed && version.needs_update();
+    state.installed_version = version.installed;
+    state.remote_version = version.remote;
+    Ok(())
 }
 
 pub fn install_addon(client: &Agent, addon: &Addon, state: Arc<Mutex<AddonState>>) -> Result<bool> {
-    let success = if addon.link.ends_with(".zip") {
-        handle_zip_install(client, addon, &state)?
+    let token = state.lock().unwrap().cancel.clone();
+    if addon.link.ends_with(".zip") {
+        handle_zip_install(client, addon, &state, &token)?;
     } else {
-        handle_file_install(client, addon, &state)?
-    };
+        handle_file_install(client, addon, &state, &token)?;
+    }
 
-    if addon.name == "NSQC" && success {
-        if let Ok(needs_update) = check_nsqc_update(client) {
-            let mut state = state.lock().unwrap();
-            state.needs_update = needs_update;
-        }
+    Ok(check_addon_installed(addon))
+}
+
+fn record_install(addon: &Addon, installed_paths: &[PathBuf]) -> Result<()> {
+    if let Some(previous) = ledger::record(&addon.name, installed_paths)? {
+        remove_stale_paths(addon, &previous, installed_paths);
     }
+    Ok(())
+}
 
-    Ok(success)
+// Удаляет то, что ставила прошлая версия аддона, но не ставит новая
+fn remove_stale_paths(addon: &Addon, previous: &LedgerEntry, installed_paths: &[PathBuf]) {
+    for path in previous.absolute_paths() {
+        let reinstalled = installed_paths
+            .iter()
+            .any(|installed| ledger::overlaps(installed, &path));
+        if reinstalled || ledger::is_owned_by_others(&addon.name, &path) {
+            continue;
+        }
+
+        info!("Deleting stale component: {}", path.display());
+        if let Err(e) = staging::remove_path(&path) {
+            warn!("Stale component deletion error: {} - {}", path.display(), e);
+        }
+    }
 }
 
 fn handle_zip_install(
     client: &Agent,
     addon: &Addon,
     state: &Arc<Mutex<AddonState>>,
-) -> Result<bool> {
+    token: &CancellationToken,
+) -> Result<()> {
     info!("🚀 Starting ZIP install: {}", addon.name);
     let temp_dir = tempdir().context("🔴 Failed to create temp dir")?;
-    let download_path = temp_dir.path().join(format!("{}.zip", addon.name));
+    let download_path = config::downloads_dir().join(format!("{}.zip", addon.name));
 
-    download_file(client, &addon.link, &download_path, state.clone())?;
+    download_package(client, addon, &download_path, state, token)?;
 
     let extract_dir = temp_dir.path().join("extracted");
     fs::create_dir_all(&extract_dir)?;
 
-    zip_extract(&download_path, &extract_dir)
-        .map_err(|e| anyhow::anyhow!("🔧 Failed to extract ZIP: {}", e))?;
+    archive::extract_safely(&download_path, &extract_dir, token)?;
+    install_extracted(addon, &extract_dir, token)?;
+    let _ = fs::remove_file(&download_path);
 
-    let entries: Vec<PathBuf> = fs::read_dir(&extract_dir)?
-        .filter_map(|e| e.ok().map(|entry| entry.path()))
+    info!("✅ Successfully installed: {}", addon.name);
+    Ok(())
+}
+
+fn install_extracted(addon: &Addon, extract_dir: &Path, token: &CancellationToken) -> Result<()> {
+    let entries: Vec<(PathBuf, OsString)> = fs::read_dir(extract_dir)?
+        .filter_map(|e| e.ok().map(|entry| (entry.path(), entry.file_name())))
         .collect();
 
     if entries.is_empty() {
         return Err(anyhow::anyhow!("📭 Empty ZIP archive"));
     }
 
-    let (source_dir, should_create_subdir) = match entries.as_slice() {
-        [single_entry] if single_entry.is_dir() => (single_entry.clone(), true),
-        _ => (extract_dir.clone(), false),
+    let target_dir = config::base_dir().join(&addon.target_path);
+    let single_dir = match entries.as_slice() {
+        [(single_entry, _)] if single_entry.is_dir() => Some(single_entry.clone()),
+        _ => None,
     };
-
-    let base_dir = config::base_dir();
-    let target_dir = base_dir.join(&addon.target_path);
-    let final_target = if should_create_subdir {
-        target_dir.join(&addon.name)
-    } else {
-        target_dir
+    let sources = match single_dir {
+        Some(source_dir) => vec![(source_dir, target_dir.join(&addon.name))],
+        None => entries
+            .into_iter()
+            .map(|(path, name)| (path, target_dir.join(name)))
+            .collect(),
     };
 
-    fs::create_dir_all(&final_target)?;
-    copy_all_contents(&source_dir, &final_target)?;
+    let _lock = staging::lock_paths(
+        sources.iter().map(|(_, target)| target.clone()).collect(),
+        token,
+    )?;
 
-    info!("✅ Successfully installed: {}", addon.name);
-    Ok(check_addon_installed(addon))
+    let owned = owned_paths(addon);
+    let mut install_entries = Vec::new();
+    for (source, target) in sources {
+        plan_install(&source, &target, &owned, &mut install_entries)?;
+    }
+
+    // В реестр попадает только созданное установкой, а не перезаписанные чужие файлы.
+    // Если установка целиком легла поверх существующих файлов, записываются они все.
+    let targets: Vec<PathBuf> = install_entries
+        .iter()
+        .map(|(_, target)| target.clone())
+        .collect();
+    let mut created: Vec<PathBuf> = targets
+        .iter()
+        .filter(|target| !target.exists() || owned.contains(target))
+        .cloned()
+        .collect();
+    if created.is_empty() {
+        created = targets;
+    }
+
+    staging::install_atomically(install_entries, token)?;
+    record_install(addon, &created)
 }
 
-fn download_file(
-    client: &Agent,
-    url: &str,
-    path: &Path,
-    state: Arc<Mutex<AddonState>>,
+// Папка заменяется целиком, только если её ещё нет или она принадлежит аддону.
+// В общие папки (Interface, Data и т.п.) содержимое вливается пофайлово.
+fn plan_install(
+    source: &Path,
+    target: &Path,
+    owned: &[PathBuf],
+    plan: &mut Vec<(PathBuf, PathBuf)>,
 ) -> Result<()> {
-    info!("⏬ Downloading: {}", url);
-    let mut attempts = 0;
-    let max_attempts = 3;
-    let total_size;
-
-    let response = loop {
-        let result = client
-            .get(url)
-            .set("User-Agent", "NightWatchUpdater/1.0")
-            .timeout(Duration::from_secs(600))
-            .call();
-
-        match result {
-            Ok(res) => {
-                total_size = res
-                    .header("Content-Length")
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(0);
-                break res;
-            }
-            Err(e) => {
-                error!("Network error (attempt {}): {}", attempts + 1, e);
-                if attempts >= max_attempts {
-                    return Err(e.into());
-                }
-                attempts += 1;
-                std::thread::sleep(Duration::from_secs(5));
-            }
-        }
-    };
-
-    let mut reader = response.into_reader();
-    let mut file = File::create(path).context("🔴 Failed to create temp file")?;
-    let mut downloaded: u64 = 0;
-    let mut buffer = [0u8; 8192];
+    let replaceable = !target.exists()
+        || owned.iter().any(|path| path == target)
+        || (source.is_file() && target.is_file());
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        file.write_all(&buffer[..bytes_read])?;
-        downloaded += bytes_read as u64;
-        state.lock().unwrap().progress = downloaded as f32 / total_size as f32;
+    if replaceable {
+        plan.push((source.to_path_buf(), target.to_path_buf()));
+        return Ok(());
     }
 
-    if total_size > 0 && downloaded != total_size {
+    if !(source.is_dir() && target.is_dir()) {
         return Err(anyhow::anyhow!(
-            "📭 File corrupted: expected {} bytes, got {}",
-            total_size,
-            downloaded
+            "🛑 Install would overwrite {}",
+            target.display()
         ));
     }
 
-    file.sync_all()?;
-    info!(
-        "✅ Downloaded: {} ({:.2} MB)",
-        url,
-        downloaded as f64 / 1024.0 / 1024.0
-    );
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        plan_install(&entry.path(), &target.join(entry.file_name()), owned, plan)?;
+    }
     Ok(())
 }
 
-fn copy_all_contents(source: &Path, dest: &Path) -> Result<()> {
-    info!("📁 Copying: [{}] -> [{}]", source.display(), dest.display());
-    fs::create_dir_all(dest)?;
+// Пути аддона по реестру. Для установок до реестра — те же папки в target_path,
+// что находит check_addon_installed, если они не записаны за другими аддонами.
+fn owned_paths(addon: &Addon) -> Vec<PathBuf> {
+    if let Some(entry) = ledger::entry(&addon.name) {
+        return entry.absolute_paths();
+    }
 
-    let options = DirCopyOptions::new().overwrite(true).content_only(true);
+    let Ok(entries) = fs::read_dir(config::base_dir().join(&addon.target_path)) else {
+        return Vec::new();
+    };
 
-    for entry in fs::read_dir(source)? {
-        let entry = entry?;
-        let entry_path = entry.path();
-        let target_path = dest.join(entry.file_name());
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            !staging::is_staging_artifact(&name) && name.contains(&addon.name)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| !ledger::is_owned_by_others(&addon.name, path))
+        .collect()
+}
+
+fn download_package(
+    client: &Agent,
+    addon: &Addon,
+    path: &Path,
+    state: &Arc<Mutex<AddonState>>,
+    token: &CancellationToken,
+) -> Result<()> {
+    fs::create_dir_all(config::downloads_dir())?;
+    downloader::download_file(client, &addon.link, path, state.clone(), token)?;
 
-        if entry_path.is_dir() {
-            fs_extra::dir::copy(&entry_path, &target_path, &options)?;
-        } else {
-            fs::copy(&entry_path, &target_path)?;
+    if let Some(expected) = &addon.sha256 {
+        if let Err(e) = downloader::verify_sha256(path, expected) {
+            let _ = fs::remove_file(path);
+            return Err(e);
         }
+        info!("🔒 Checksum verified: {}", addon.name);
     }
 
     Ok(())
@@ -203,8 +282,48 @@ fn copy_all_contents(source: &Path, dest: &Path) -> Result<()> {
 
 pub fn uninstall_addon(addon: &Addon) -> Result<bool> {
     info!("Starting uninstall: {}", addon.name);
+    let success = match ledger::entry(&addon.name) {
+        Some(entry) => uninstall_from_ledger(addon, &entry)?,
+        None => uninstall_heuristic(addon)?,
+    };
+
+    if success {
+        info!("Uninstall successful: {}", addon.name);
+    } else {
+        warn!("Partial uninstall: {}", addon.name);
+    }
+    Ok(success && !check_addon_installed(addon))
+}
+
+fn uninstall_from_ledger(addon: &Addon, entry: &LedgerEntry) -> Result<bool> {
+    let _lock = staging::lock_paths(entry.absolute_paths(), &CancellationToken::default())?;
+    let mut remaining = Vec::new();
+
+    for path in entry.absolute_paths() {
+        if ledger::is_owned_by_others(&addon.name, &path) {
+            info!("Keeping shared component: {}", path.display());
+            continue;
+        }
+
+        info!("Deleting component: {}", path.display());
+        if let Err(e) = staging::remove_path(&path) {
+            error!("Component deletion error: {} - {}", path.display(), e);
+            remaining.push(path);
+        }
+    }
+
+    ledger::record(&addon.name, &remaining)?;
+    Ok(remaining.is_empty())
+}
+
+// Для аддонов, установленных до появления реестра
+fn uninstall_heuristic(addon: &Addon) -> Result<bool> {
     let base_dir = config::base_dir();
     let main_path = base_dir.join(&addon.target_path).join(&addon.name);
+    let _lock = staging::lock_paths(
+        vec![base_dir.join(&addon.target_path)],
+        &CancellationToken::default(),
+    )?;
     let mut success = true;
 
     if main_path.exists() {
@@ -248,28 +367,232 @@ pub fn uninstall_addon(addon: &Addon) -> Result<bool> {
     }
 
     if success {
-        info!("Uninstall successful: {}", addon.name);
-    } else {
-        warn!("Partial uninstall: {}", addon.name);
+        ledger::record(&addon.name, &[])?;
     }
-    Ok(success && !check_addon_installed(addon))
+    Ok(success)
 }
 
 fn handle_file_install(
     client: &Agent,
     addon: &Addon,
     state: &Arc<Mutex<AddonState>>,
-) -> Result<bool> {
+    token: &CancellationToken,
+) -> Result<()> {
     info!("Installing file: {}", addon.name);
-    let temp_dir = tempdir()?;
-    let download_path = temp_dir.path().join(&addon.name);
-    download_file(client, &addon.link, &download_path, state.clone())?;
+    let download_path = config::downloads_dir().join(&addon.name);
+    download_package(client, addon, &download_path, state, token)?;
 
     let base_dir = config::base_dir();
     let install_path = base_dir.join(&addon.target_path).join(&addon.name);
-    fs::create_dir_all(install_path.parent().unwrap())?;
-    fs::copy(&download_path, &install_path)?;
+    let _lock = staging::lock_paths(vec![install_path.clone()], token)?;
+    let installed_paths =
+        staging::install_atomically(vec![(download_path.clone(), install_path)], token)?;
+    record_install(addon, &installed_paths)?;
+    let _ = fs::remove_file(&download_path);
+
+    info!("File installed: {}", addon.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    // Каталог игры общий для процесса, поэтому тесты с ним идут по очереди
+    static BASE_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    fn addon(name: &str, target_path: &str) -> Addon {
+        Addon {
+            name: name.to_string(),
+            link: String::new(),
+            description: String::new(),
+            target_path: target_path.to_string(),
+            version_url: None,
+            version_file: None,
+            sha256: None,
+            depends: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
 
-    info!("File installed: {}", install_path.display());
-    Ok(install_path.exists())
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn install(addon: &Addon, package: &TempDir) -> bool {
+        install_extracted(addon, package.path(), &CancellationToken::default()).unwrap();
+        check_addon_installed(addon)
+    }
+
+    #[test]
+    fn merges_into_existing_shared_folders() {
+        let _guard = BASE_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let game = tempdir().unwrap();
+        config::set_base_dir(Some(game.path().to_path_buf()));
+
+        write(&game.path().join("Interface/AddOns/Other/keep.lua"), "keep");
+        write(&game.path().join("Data/common.MPQ"), "keep");
+
+        let package = tempdir().unwrap();
+        write(
+            &package.path().join("Interface/AddOns/Pack/pack.lua"),
+            "new",
+        );
+        write(&package.path().join("Data/patch-x.MPQ"), "new");
+
+        let pack = addon("Pack", "");
+        assert!(install(&pack, &package));
+
+        assert!(game
+            .path()
+            .join("Interface/AddOns/Other/keep.lua")
+            .is_file());
+        assert!(game.path().join("Data/common.MPQ").is_file());
+        assert!(game.path().join("Interface/AddOns/Pack/pack.lua").is_file());
+        assert!(game.path().join("Data/patch-x.MPQ").is_file());
+
+        let mut paths = ledger::entry("Pack").unwrap().paths;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Data/patch-x.MPQ"),
+                PathBuf::from("Interface/AddOns/Pack")
+            ]
+        );
+
+        assert!(uninstall_addon(&pack).unwrap());
+        assert!(game
+            .path()
+            .join("Interface/AddOns/Other/keep.lua")
+            .is_file());
+        assert!(game.path().join("Data/common.MPQ").is_file());
+    }
+
+    #[test]
+    fn refuses_to_replace_foreign_folder_with_file() {
+        let _guard = BASE_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let game = tempdir().unwrap();
+        config::set_base_dir(Some(game.path().to_path_buf()));
+
+        write(&game.path().join("Interface/keep.lua"), "keep");
+
+        let package = tempdir().unwrap();
+        write(&package.path().join("Interface"), "file");
+        write(&package.path().join("readme.txt"), "new");
+
+        let result = install_extracted(
+            &addon("Broken", ""),
+            package.path(),
+            &CancellationToken::default(),
+        );
+        assert!(result.is_err());
+        assert!(ledger::entry("Broken").is_none());
+        assert!(game.path().join("Interface/keep.lua").is_file());
+    }
+
+    #[test]
+    fn parallel_installs_sharing_a_new_folder() {
+        let _guard = BASE_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let game = tempdir().unwrap();
+        config::set_base_dir(Some(game.path().to_path_buf()));
+
+        let packages: Vec<(Addon, TempDir)> = ["Alpha", "Beta"]
+            .into_iter()
+            .map(|name| {
+                let package = tempdir().unwrap();
+                write(&package.path().join(name).join("main.lua"), name);
+                for index in 0..200 {
+                    let file = format!("LibStub/file{}.lua", index);
+                    write(&package.path().join(file), name);
+                }
+                (addon(name, "Interface/AddOns"), package)
+            })
+            .collect();
+
+        std::thread::scope(|scope| {
+            for (addon, package) in &packages {
+                scope.spawn(move || {
+                    install_extracted(addon, package.path(), &CancellationToken::default()).unwrap()
+                });
+            }
+        });
+
+        let addons = game.path().join("Interface/AddOns");
+        let mut names: Vec<String> = fs::read_dir(&addons)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Alpha", "Beta", "LibStub"]);
+        assert_eq!(fs::read_dir(addons.join("LibStub")).unwrap().count(), 200);
+    }
+
+    #[test]
+    fn updates_pre_ledger_multi_folder_addon() {
+        let _guard = BASE_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let game = tempdir().unwrap();
+        config::set_base_dir(Some(game.path().to_path_buf()));
+
+        let addons = game.path().join("Interface/AddOns");
+        write(&addons.join("DBM-Core/old.lua"), "old");
+        write(&addons.join("DBM-GUI/old.lua"), "old");
+        write(&addons.join("Other/keep.lua"), "keep");
+
+        let package = tempdir().unwrap();
+        write(&package.path().join("DBM-Core/new.lua"), "new");
+        write(&package.path().join("DBM-GUI/new.lua"), "new");
+
+        let dbm = addon("DBM", "Interface/AddOns");
+        assert!(install(&dbm, &package));
+
+        let entry = ledger::entry("DBM").unwrap();
+        assert!(entry
+            .paths
+            .contains(&PathBuf::from("Interface/AddOns/DBM-Core")));
+        assert!(entry
+            .paths
+            .contains(&PathBuf::from("Interface/AddOns/DBM-GUI")));
+        assert!(addons.join("DBM-Core/new.lua").is_file());
+        assert!(!addons.join("DBM-Core/old.lua").exists());
+
+        assert!(uninstall_addon(&dbm).unwrap());
+        assert!(!addons.join("DBM-Core").exists());
+        assert!(!addons.join("DBM-GUI").exists());
+        assert!(addons.join("Other/keep.lua").is_file());
+    }
+
+    #[test]
+    fn overwriting_existing_files_is_still_recorded() {
+        let _guard = BASE_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let game = tempdir().unwrap();
+        config::set_base_dir(Some(game.path().to_path_buf()));
+
+        write(&game.path().join("Fonts/FRIZQT__.TTF"), "old");
+        write(&game.path().join("Fonts/ARIALN.TTF"), "old");
+        write(&game.path().join("Interface/FrameXML/Fonts.xml"), "old");
+
+        let package = tempdir().unwrap();
+        write(&package.path().join("Fonts/FRIZQT__.TTF"), "new");
+        write(&package.path().join("Interface/FrameXML/Fonts.xml"), "new");
+
+        let font = addon("CustomFont", "");
+        assert!(install(&font, &package));
+        let mut paths = ledger::entry("CustomFont").unwrap().paths;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Fonts/FRIZQT__.TTF"),
+                PathBuf::from("Interface/FrameXML/Fonts.xml")
+            ]
+        );
+        assert!(game.path().join("Fonts/ARIALN.TTF").is_file());
+        assert_eq!(
+            fs::read_to_string(game.path().join("Fonts/FRIZQT__.TTF")).unwrap(),
+            "new"
+        );
+    }
 }
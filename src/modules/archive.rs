@@ -0,0 +1,193 @@
+use crate::modules::cancellation::{self, CancellationToken};
+use anyhow::{Context, Result};
+use log::info;
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path},
+};
+use zip::ZipArchive;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+pub fn extract_safely(
+    archive_path: &Path,
+    extract_dir: &Path,
+    token: &CancellationToken,
+) -> Result<()> {
+    validate_archive(archive_path)?;
+
+    let file = File::open(archive_path).context("🔴 Failed to open archive")?;
+    let mut archive = ZipArchive::new(file).context("🔧 Failed to read ZIP")?;
+
+    for index in 0..archive.len() {
+        token.check()?;
+
+        let mut entry = archive.by_index(index)?;
+        let relative = entry
+            .enclosed_name()
+            .with_context(|| format!("🛑 Unsafe archive entry: {}", entry.name()))?;
+        let output = extract_dir.join(relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&output)?;
+            continue;
+        }
+
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut writer = File::create(&output)
+            .with_context(|| format!("🔧 Failed to extract: {}", output.display()))?;
+        cancellation::copy(&mut entry, &mut writer, token)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&output, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+    }
+
+    info!("📦 Extracted {} entries", archive.len());
+    Ok(())
+}
+
+fn is_symlink(mode: Option<u32>) -> bool {
+    mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+}
+
+// Отклоняет архив целиком, если хоть одна запись выходит за каталог распаковки.
+// Ссылки не допускаются вовсе: в аддонах они не встречаются, а цепочка ссылок
+// из предыдущих записей выводит следующие записи за каталог распаковки.
+fn validate_archive(archive_path: &Path) -> Result<()> {
+    let file = File::open(archive_path).context("🔴 Failed to open archive")?;
+    let mut archive = ZipArchive::new(file).context("🔧 Failed to read ZIP")?;
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let name = entry.name().to_string();
+
+        if is_unsafe_path(&name) || entry.enclosed_name().is_none() {
+            return Err(anyhow::anyhow!("🛑 Unsafe archive entry: {}", name));
+        }
+
+        if is_symlink(entry.unix_mode()) {
+            let mut target = String::new();
+            entry.read_to_string(&mut target)?;
+            return Err(anyhow::anyhow!(
+                "🛑 Symlinks are not allowed in archives: {} -> {}",
+                name,
+                target
+            ));
+        }
+    }
+
+    info!("🔍 Archive validated: {} entries", archive.len());
+    Ok(())
+}
+
+fn is_unsafe_path(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+
+    name.starts_with('/')
+        || has_drive_letter(&name)
+        || name.split('/').any(|part| part == "..")
+        || Path::new(&name)
+            .components()
+            .any(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+}
+
+fn has_drive_letter(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::{tempdir, TempDir};
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    enum Entry {
+        File(&'static str),
+        Symlink(&'static str, &'static str),
+    }
+
+    fn extract(entries: &[Entry]) -> (TempDir, Result<()>) {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("addon.zip");
+
+        let mut writer = ZipWriter::new(File::create(&archive_path).unwrap());
+        for entry in entries {
+            match entry {
+                Entry::File(name) => {
+                    writer
+                        .start_file(*name, SimpleFileOptions::default())
+                        .unwrap();
+                    writer.write_all(b"pwned").unwrap();
+                }
+                Entry::Symlink(name, target) => writer
+                    .add_symlink(*name, *target, SimpleFileOptions::default())
+                    .unwrap(),
+            }
+        }
+        writer.finish().unwrap();
+
+        let extract_dir = dir.path().join("a").join("b").join("extracted");
+        fs::create_dir_all(&extract_dir).unwrap();
+        let result = extract_safely(&archive_path, &extract_dir, &CancellationToken::default());
+        (dir, result)
+    }
+
+    #[test]
+    fn extracts_regular_entries() {
+        let (dir, result) = extract(&[Entry::File("Addon/Addon.toc")]);
+        assert!(result.is_ok());
+        assert!(dir.path().join("a/b/extracted/Addon/Addon.toc").is_file());
+    }
+
+    #[test]
+    fn rejects_symlink_chain() {
+        let (dir, result) = extract(&[
+            Entry::Symlink("a", "."),
+            Entry::Symlink("a/a/b", "../.."),
+            Entry::File("a/a/b/pwned.txt"),
+        ]);
+        assert!(result.is_err());
+        assert!(!dir.path().join("a/pwned.txt").exists());
+        assert!(!dir.path().join("a/b/pwned.txt").exists());
+        assert!(!dir.path().join("a/b/extracted/a").exists());
+    }
+
+    #[test]
+    fn rejects_symlink_inside_root() {
+        let (_dir, result) = extract(&[Entry::Symlink("Addon/link", "Addon.toc")]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_parent_dir() {
+        let (dir, result) = extract(&[Entry::File("Addon/../../pwned.txt")]);
+        assert!(result.is_err());
+        assert!(!dir.path().join("a/b/pwned.txt").exists());
+    }
+
+    #[test]
+    fn rejects_absolute_path() {
+        let (_dir, result) = extract(&[Entry::File("/tmp/pwned.txt")]);
+        assert!(result.is_err());
+        assert!(is_unsafe_path("\\Windows\\pwned.txt"));
+    }
+
+    #[test]
+    fn rejects_drive_letter() {
+        let (_dir, result) = extract(&[Entry::File("C:/pwned.txt")]);
+        assert!(result.is_err());
+        assert!(is_unsafe_path("C:\\pwned.txt"));
+        assert!(is_unsafe_path("c:pwned.txt"));
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "⏹ Отменено пользователем")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.is::<Cancelled>()
+}
+
+// Копирование блоками с проверкой отмены между ними
+pub fn copy(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    token: &CancellationToken,
+) -> Result<u64> {
+    let mut buffer = [0u8; 65536];
+    let mut copied = 0;
+
+    loop {
+        token.check()?;
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            return Ok(copied);
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        copied += bytes_read as u64;
+    }
+}
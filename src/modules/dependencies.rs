@@ -0,0 +1,67 @@
+use crate::app::Addon;
+use anyhow::Result;
+
+// Порядок установки: сначала зависимости (транзитивно), в конце сам аддон
+pub fn install_order(addons: &[Addon], name: &str) -> Result<Vec<String>> {
+    let mut order = Vec::new();
+    let mut visiting = Vec::new();
+    visit(addons, name, &mut visiting, &mut order)?;
+    Ok(order)
+}
+
+pub fn dependents<'a>(addons: &'a [Addon], name: &str) -> Vec<&'a Addon> {
+    addons
+        .iter()
+        .filter(|addon| addon.depends.iter().any(|dep| dep == name))
+        .collect()
+}
+
+// Конфликт симметричен: достаточно объявления у любой из сторон
+pub fn conflicts<'a>(addons: &'a [Addon], name: &str) -> Vec<&'a Addon> {
+    let declared = addons
+        .iter()
+        .find(|addon| addon.name == name)
+        .map(|addon| addon.conflicts.as_slice())
+        .unwrap_or_default();
+
+    addons
+        .iter()
+        .filter(|addon| addon.name != name)
+        .filter(|addon| {
+            declared.contains(&addon.name) || addon.conflicts.iter().any(|other| other == name)
+        })
+        .collect()
+}
+
+fn visit(
+    addons: &[Addon],
+    name: &str,
+    visiting: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<()> {
+    if order.iter().any(|done| done == name) {
+        return Ok(());
+    }
+
+    if visiting.iter().any(|current| current == name) {
+        return Err(anyhow::anyhow!(
+            "🔁 Dependency cycle: {} -> {}",
+            visiting.join(" -> "),
+            name
+        ));
+    }
+
+    let addon = addons
+        .iter()
+        .find(|addon| addon.name == name)
+        .ok_or_else(|| anyhow::anyhow!("❓ Unknown dependency: {}", name))?;
+
+    visiting.push(name.to_string());
+    for dep in &addon.depends {
+        visit(addons, dep, visiting, order)?;
+    }
+    visiting.pop();
+
+    order.push(name.to_string());
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use crate::app::AddonState;
+use crate::modules::cancellation::{self, CancellationToken};
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use ureq::Agent;
+
+const SPEED_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+// Скорость сглаживается, чтобы оценка оставшегося времени не скакала
+struct Throughput {
+    since: Instant,
+    bytes: u64,
+    speed: Option<f64>,
+}
+
+impl Throughput {
+    fn new(bytes: u64) -> Self {
+        Self {
+            since: Instant::now(),
+            bytes,
+            speed: None,
+        }
+    }
+
+    fn sample(&mut self, bytes: u64) -> Option<f64> {
+        let elapsed = self.since.elapsed();
+        if elapsed < SPEED_SAMPLE_INTERVAL {
+            return None;
+        }
+
+        let rate = bytes.saturating_sub(self.bytes) as f64 / elapsed.as_secs_f64();
+        let speed = match self.speed {
+            Some(speed) => 0.3 * rate + 0.7 * speed,
+            None => rate,
+        };
+
+        self.since = Instant::now();
+        self.bytes = bytes;
+        self.speed = Some(speed);
+        Some(speed)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    validator: Option<String>,
+}
+
+pub fn download_file(
+    client: &Agent,
+    url: &str,
+    path: &Path,
+    state: Arc<Mutex<AddonState>>,
+    token: &CancellationToken,
+) -> Result<()> {
+    info!("⏬ Downloading: {}", url);
+    let partial_path = with_suffix(path, ".part");
+    let meta_path = with_suffix(path, ".part.json");
+    let mut attempts = 0;
+    let max_attempts = 3;
+
+    loop {
+        match download_attempt(client, url, &partial_path, &meta_path, &state, token) {
+            Ok(()) => break,
+            // Недокачанный файл остаётся, чтобы следующая попытка продолжила с того же места
+            Err(e) if cancellation::is_cancelled(&e) => {
+                info!("⏹ Download cancelled: {}", url);
+                return Err(e);
+            }
+            Err(e) => {
+                error!("Network error (attempt {}): {}", attempts + 1, e);
+                if attempts >= max_attempts {
+                    return Err(e);
+                }
+                attempts += 1;
+                std::thread::sleep(Duration::from_secs(5));
+                token.check()?;
+            }
+        }
+    }
+
+    fs::rename(&partial_path, path).context("🔴 Failed to finalize download")?;
+    let _ = fs::remove_file(&meta_path);
+    Ok(())
+}
+
+fn download_attempt(
+    client: &Agent,
+    url: &str,
+    partial_path: &Path,
+    meta_path: &Path,
+    state: &Arc<Mutex<AddonState>>,
+    token: &CancellationToken,
+) -> Result<()> {
+    // Докачка возможна только для того же URL и при наличии валидатора для If-Range
+    let resume = read_partial_meta(meta_path)
+        .filter(|meta| meta.url == url)
+        .and_then(|meta| meta.validator)
+        .and_then(|validator| {
+            let size = fs::metadata(partial_path).ok()?.len();
+            (size > 0).then_some((size, validator))
+        });
+
+    let mut request = client
+        .get(url)
+        .set("User-Agent", "NightWatchUpdater/1.0")
+        .timeout(Duration::from_secs(600));
+
+    if let Some((offset, validator)) = &resume {
+        info!("↪ Resuming from {} bytes: {}", offset, url);
+        request = request
+            .set("Range", &format!("bytes={}-", offset))
+            .set("If-Range", validator);
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, _)) => {
+            discard_partial(partial_path, meta_path);
+            return Err(anyhow::anyhow!("Stale partial download discarded"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let offset = match (&resume, response.status()) {
+        (Some((offset, _)), 206) => *offset,
+        _ => 0,
+    };
+    let resumed = offset > 0;
+    if resume.is_some() && !resumed {
+        warn!("Server ignored range request, restarting download: {}", url);
+    }
+
+    let total_size = response
+        .header("Content-Length")
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(|len| len + offset);
+
+    write_partial_meta(
+        meta_path,
+        &PartialDownload {
+            url: url.to_string(),
+            validator: response_validator(&response),
+        },
+    )?;
+
+    let mut file = if resumed {
+        OpenOptions::new().append(true).open(partial_path)?
+    } else {
+        File::create(partial_path).context("🔴 Failed to create download file")?
+    };
+
+    let mut reader = response.into_reader();
+    let mut downloaded: u64 = offset;
+    let mut buffer = [0u8; 8192];
+    let mut throughput = Throughput::new(offset);
+
+    {
+        let mut state = state.lock().unwrap();
+        state.downloaded = offset;
+        state.total = total_size;
+        state.speed = 0.0;
+    }
+
+    loop {
+        token.check()?;
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..bytes_read])?;
+        downloaded += bytes_read as u64;
+
+        let mut state = state.lock().unwrap();
+        state.downloaded = downloaded;
+        if let Some(total) = total_size.filter(|&total| total > 0) {
+            state.progress = downloaded as f32 / total as f32;
+        }
+        if let Some(speed) = throughput.sample(downloaded) {
+            state.speed = speed;
+        }
+    }
+
+    file.sync_all()?;
+
+    if let Some(total_size) = total_size.filter(|&total| total > 0 && total != downloaded) {
+        if downloaded > total_size {
+            discard_partial(partial_path, meta_path);
+        }
+        return Err(anyhow::anyhow!(
+            "📭 File corrupted: expected {} bytes, got {}",
+            total_size,
+            downloaded
+        ));
+    }
+
+    info!(
+        "✅ Downloaded: {} ({:.2} MB)",
+        url,
+        downloaded as f64 / 1024.0 / 1024.0
+    );
+    Ok(())
+}
+
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let mut file = File::open(path).context("🔴 Failed to open downloaded file")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+
+    let actual: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(anyhow::anyhow!(
+            "🛑 Checksum mismatch: expected {}, got {}",
+            expected.trim(),
+            actual
+        ));
+    }
+
+    Ok(())
+}
+
+fn response_validator(response: &ureq::Response) -> Option<String> {
+    // Слабые ETag нельзя использовать в If-Range
+    response
+        .header("ETag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("Last-Modified"))
+        .map(str::to_string)
+}
+
+fn read_partial_meta(path: &Path) -> Option<PartialDownload> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn write_partial_meta(path: &Path, meta: &PartialDownload) -> Result<()> {
+    fs::write(path, serde_json::to_string(meta)?)?;
+    Ok(())
+}
+
+fn discard_partial(partial_path: &Path, meta_path: &Path) {
+    let _ = fs::remove_file(partial_path);
+    let _ = fs::remove_file(meta_path);
+}
+
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -0,0 +1,46 @@
+use crate::config;
+use anyhow::{Context, Result};
+use log::info;
+use std::fs;
+use std::path::Path;
+
+// Кэш клиента, который игра сама создаёт заново; другие папки не трогаются
+const CACHE_DIRS: [&str; 2] = ["Cache/WDB", "WDB"];
+
+// Возвращает число освобождённых байт
+pub fn clear() -> Result<u64> {
+    let mut freed = 0;
+
+    for dir in CACHE_DIRS {
+        let path = config::base_dir().join(dir);
+        if !path.is_dir() {
+            continue;
+        }
+
+        let size = dir_size(&path);
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("🔴 Failed to remove {}", path.display()))?;
+        info!("Removed {} ({})", path.display(), config::format_size(size));
+        freed += size;
+    }
+
+    Ok(freed)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let meta = fs::symlink_metadata(entry.path()).ok()?;
+            Some(if meta.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                meta.len()
+            })
+        })
+        .sum()
+}
@@ -0,0 +1,189 @@
+use crate::config::{self, LaunchSettings};
+use anyhow::{Context, Result};
+use log::info;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+pub const DEFAULT_TEMPLATE: &str = "{exe} {args}";
+#[cfg(not(target_os = "windows"))]
+pub const DEFAULT_TEMPLATE: &str = "{wine} {exe} {args}";
+
+pub const DEFAULT_WINE: &str = "wine";
+#[cfg(unix)]
+const WINE_BINARIES: [&str; 5] = [
+    "wine",
+    "wine64",
+    "wine-stable",
+    "wine-staging",
+    "wine-development",
+];
+
+pub struct LaunchCommand {
+    env: Vec<(String, String)>,
+    program: String,
+    args: Vec<String>,
+}
+
+impl LaunchCommand {
+    fn spawn(&self) -> Result<()> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .current_dir(config::base_dir());
+
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000);
+        }
+
+        command
+            .spawn()
+            .with_context(|| format!("🔴 Failed to start {}", self.program))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for LaunchCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .chain(std::iter::once(self.program.clone()))
+            .chain(self.args.iter().cloned())
+            .collect();
+        write!(f, "{}", shell_words::join(words))
+    }
+}
+
+// Шаблон вида "VAR=значение обёртка {exe} {args}": переменные окружения в начале,
+// {exe} заменяется на путь к Wow.exe, {wine} — на программу Wine, {args} — на аргументы
+pub fn prepare(launch: &LaunchSettings) -> Result<LaunchCommand> {
+    let exe = config::get_wow_path().to_string_lossy().into_owned();
+    let wine = match launch.wine.binary.trim() {
+        "" => DEFAULT_WINE,
+        binary => binary,
+    };
+    let arguments = shell_words::split(&launch.arguments).context("Ошибка в аргументах запуска")?;
+    let template = match launch.template.trim() {
+        "" => DEFAULT_TEMPLATE,
+        template => template,
+    };
+    let words = shell_words::split(template).context("Ошибка в команде запуска")?;
+
+    let mut env = wine_env(launch)?;
+    let mut command = Vec::new();
+
+    for word in words {
+        if word == "{args}" {
+            command.extend(arguments.iter().cloned());
+            continue;
+        }
+
+        match env_assignment(&word) {
+            Some((key, value)) if command.is_empty() => {
+                env.push((key.to_string(), expand(value, &exe, wine)));
+            }
+            _ => command.push(expand(&word, &exe, wine)),
+        }
+    }
+
+    let (program, args) = command
+        .split_first()
+        .context("В команде запуска нет программы")?;
+
+    Ok(LaunchCommand {
+        env,
+        program: program.clone(),
+        args: args.to_vec(),
+    })
+}
+
+pub fn launch_game(launch: &LaunchSettings) -> Result<()> {
+    let command = prepare(launch)?;
+    info!("Launching: {}", command);
+    command.spawn()
+}
+
+fn expand(word: &str, exe: &str, wine: &str) -> String {
+    word.replace("{exe}", exe).replace("{wine}", wine)
+}
+
+fn wine_env(launch: &LaunchSettings) -> Result<Vec<(String, String)>> {
+    let mut env = Vec::new();
+
+    let prefix = launch.wine.prefix.trim();
+    if !prefix.is_empty() {
+        env.push(("WINEPREFIX".to_string(), expand_prefix(prefix)?));
+    }
+
+    for word in shell_words::split(&launch.wine.env).context("Ошибка в переменных Wine")?
+    {
+        let (key, value) =
+            env_assignment(&word).with_context(|| format!("Ожидается ИМЯ=значение: {}", word))?;
+        env.push((key.to_string(), value.to_string()));
+    }
+
+    Ok(env)
+}
+
+// Wine принимает только абсолютный путь к префиксу; ~ раскрывается в домашнюю папку
+fn expand_prefix(prefix: &str) -> Result<String> {
+    let path = match prefix.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = std::env::var_os("HOME").context("Не задана переменная HOME")?;
+            let mut path = PathBuf::from(home);
+            let rest = rest.trim_start_matches('/');
+            if !rest.is_empty() {
+                path.push(rest);
+            }
+            path
+        }
+        _ => PathBuf::from(prefix),
+    };
+
+    if !path.is_absolute() {
+        return Err(anyhow::anyhow!(
+            "Префикс Wine должен быть абсолютным путём: {}",
+            prefix
+        ));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+// Программы Wine, найденные в PATH
+#[cfg(unix)]
+pub fn detect_wine() -> Vec<std::path::PathBuf> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<_> = std::env::split_paths(&path)
+        .flat_map(|dir| WINE_BINARIES.iter().map(move |name| dir.join(name)))
+        .filter(|candidate| is_executable(candidate))
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+fn env_assignment(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once('=')?;
+    let mut chars = key.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some((key, value))
+}
@@ -0,0 +1,114 @@
+use crate::config;
+use anyhow::Result;
+use indexmap::IndexMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Default, Serialize, Deserialize)]
+struct Ledger {
+    addons: IndexMap<String, LedgerEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    // Созданные установкой файлы и папки относительно каталога игры,
+    // без общих родительских папок вроде Interface или Data
+    pub paths: Vec<PathBuf>,
+    pub installed_at: u64,
+}
+
+impl LedgerEntry {
+    pub fn absolute_paths(&self) -> Vec<PathBuf> {
+        let base_dir = config::base_dir();
+        self.paths.iter().map(|path| base_dir.join(path)).collect()
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.absolute_paths().iter().any(|path| path.exists())
+    }
+}
+
+pub fn entry(name: &str) -> Option<LedgerEntry> {
+    let _guard = lock();
+    load().addons.get(name).cloned()
+}
+
+// Записывает актуальный набор путей аддона и возвращает предыдущую запись
+pub fn record(name: &str, paths: &[PathBuf]) -> Result<Option<LedgerEntry>> {
+    let _guard = lock();
+    let mut ledger = load();
+    let base_dir = config::base_dir();
+
+    let entry = LedgerEntry {
+        paths: paths
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&base_dir)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|_| path.clone())
+            })
+            .collect(),
+        installed_at: config::unix_now(),
+    };
+
+    let previous = ledger.addons.insert(name.to_string(), entry);
+    save(&ledger)?;
+    Ok(previous)
+}
+
+// Путь нельзя удалять, если он сам, что-то внутри него или содержащая его папка
+// принадлежит другому аддону
+pub fn is_owned_by_others(name: &str, path: &Path) -> bool {
+    let _guard = lock();
+    load()
+        .addons
+        .iter()
+        .filter(|(other, _)| other.as_str() != name)
+        .any(|(_, entry)| {
+            entry
+                .absolute_paths()
+                .iter()
+                .any(|owned| overlaps(owned, path))
+        })
+}
+
+pub fn overlaps(a: &Path, b: &Path) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+fn ledger_path() -> PathBuf {
+    config::data_dir().join("ledger.json")
+}
+
+fn lock() -> MutexGuard<'static, ()> {
+    LEDGER_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn load() -> Ledger {
+    let text = match fs::read_to_string(ledger_path()) {
+        Ok(text) => text,
+        Err(_) => return Ledger::default(),
+    };
+
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        warn!("Ledger is corrupted, starting over: {}", e);
+        Ledger::default()
+    })
+}
+
+fn save(ledger: &Ledger) -> Result<()> {
+    let path = ledger_path();
+    let temp_path = path.with_extension("json.tmp");
+
+    fs::create_dir_all(config::data_dir())?;
+    fs::write(&temp_path, serde_json::to_string_pretty(ledger)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use simplelog::{Config, SharedLogger};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const CAPACITY: usize = 2000;
+
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub time: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    pub fn line(&self) -> String {
+        format!("{} [{}] {}", self.time, self.level, self.message)
+    }
+}
+
+// Хранит последние записи журнала в памяти для панели в окне программы
+pub struct RingLogger {
+    level: LevelFilter,
+    config: Config,
+}
+
+impl RingLogger {
+    pub fn new(level: LevelFilter) -> Box<Self> {
+        Box::new(Self {
+            level,
+            config: Config::default(),
+        })
+    }
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let secs = crate::config::unix_now() % 86_400;
+        let entry = LogRecord {
+            level: record.level(),
+            time: format!(
+                "{:02}:{:02}:{:02}",
+                secs / 3_600,
+                secs % 3_600 / 60,
+                secs % 60
+            ),
+            message: record.args().to_string(),
+        };
+
+        let mut records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() >= CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(entry);
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for RingLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+pub fn records(level: LevelFilter) -> Vec<LogRecord> {
+    RECORDS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|record| record.level <= level)
+        .cloned()
+        .collect()
+}
@@ -1 +1,14 @@
 pub mod addon_manager;
+pub mod archive;
+pub mod cancellation;
+pub mod dependencies;
+pub mod downloader;
+pub mod game_cache;
+pub mod launcher;
+pub mod ledger;
+pub mod log_buffer;
+pub mod operations;
+pub mod queue;
+pub mod realmlist;
+pub mod self_update;
+pub mod staging;
@@ -0,0 +1,247 @@
+use crate::app::{Addon, AddonState};
+use crate::modules::cancellation::Cancelled;
+use crate::modules::{addon_manager, cancellation, dependencies};
+use anyhow::Result;
+use indexmap::IndexMap;
+use log::error;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+use ureq::Agent;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Install,
+    Uninstall,
+}
+
+impl Operation {
+    pub fn installs(self) -> bool {
+        self == Operation::Install
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operation::Install => "install",
+            Operation::Uninstall => "uninstall",
+        }
+    }
+}
+
+pub type AddonEntry = (Addon, Arc<Mutex<AddonState>>);
+pub type Step = (Addon, Arc<Mutex<AddonState>>, Operation);
+
+pub fn build_entries(addons: IndexMap<String, Addon>) -> Vec<AddonEntry> {
+    addons
+        .into_iter()
+        .map(|(_, addon)| {
+            let installed = addon_manager::check_addon_installed(&addon);
+            let installed_version = installed
+                .then(|| addon_manager::installed_version(&addon))
+                .flatten();
+
+            (
+                addon,
+                Arc::new(Mutex::new(AddonState {
+                    target_state: Some(installed),
+                    installed_version,
+                    ..Default::default()
+                })),
+            )
+        })
+        .collect()
+}
+
+pub fn step(entries: &[AddonEntry], index: usize, operation: Operation) -> Step {
+    let (addon, state) = &entries[index];
+    (addon.clone(), state.clone(), operation)
+}
+
+pub fn index_of(entries: &[AddonEntry], name: &str) -> Option<usize> {
+    entries.iter().position(|(addon, _)| addon.name == name)
+}
+
+pub fn is_installed(entries: &[AddonEntry], index: usize) -> bool {
+    entries[index]
+        .1
+        .lock()
+        .unwrap()
+        .target_state
+        .unwrap_or(false)
+}
+
+// Шаги установки с зависимостями и установленные аддоны, конфликтующие с ними
+pub fn install_plan(entries: &[AddonEntry], index: usize) -> Result<(Vec<Step>, Vec<usize>)> {
+    let addons = addon_list(entries);
+    let order = dependencies::install_order(&addons, &addons[index].name)?;
+    let indices: Vec<usize> = order
+        .iter()
+        .filter_map(|name| index_of(entries, name))
+        .collect();
+
+    let mut conflicting: Vec<usize> = indices
+        .iter()
+        .flat_map(|&i| dependencies::conflicts(&addons, &addons[i].name))
+        .filter_map(|addon| index_of(entries, &addon.name))
+        .filter(|i| !indices.contains(i) && is_installed(entries, *i))
+        .collect();
+    conflicting.sort_unstable();
+    conflicting.dedup();
+
+    let steps = indices
+        .into_iter()
+        .filter(|&i| i == index || !is_installed(entries, i))
+        .map(|i| step(entries, i, Operation::Install))
+        .collect();
+
+    Ok((steps, conflicting))
+}
+
+pub fn installed_dependents(entries: &[AddonEntry], index: usize) -> Vec<String> {
+    let addons = addon_list(entries);
+    dependencies::dependents(&addons, &addons[index].name)
+        .into_iter()
+        .filter(|addon| index_of(entries, &addon.name).is_some_and(|i| is_installed(entries, i)))
+        .map(|addon| addon.name.clone())
+        .collect()
+}
+
+fn addon_list(entries: &[AddonEntry]) -> Vec<Addon> {
+    entries.iter().map(|(addon, _)| addon.clone()).collect()
+}
+
+pub fn mark_pending(steps: &[Step]) {
+    for (_, state, operation) in steps {
+        let mut state = state.lock().unwrap();
+        state.installing = true;
+        state.target_state = Some(operation.installs());
+        state.error = None;
+    }
+}
+
+// Шаги выполняются по порядку; после первой ошибки остальные отменяются
+pub fn run_sequence(client: &Agent, steps: &[Step]) -> Result<()> {
+    run_sequence_with(client, steps, |_| {})
+}
+
+// on_start получает номер шага, когда тот действительно начинается
+pub fn run_sequence_with(
+    client: &Agent,
+    steps: &[Step],
+    mut on_start: impl FnMut(usize),
+) -> Result<()> {
+    mark_pending(steps);
+
+    for (position, (addon, state, operation)) in steps.iter().enumerate() {
+        on_start(position);
+        if let Err(e) = run(client, addon, state, *operation) {
+            let reason = if cancellation::is_cancelled(&e) {
+                e.to_string()
+            } else {
+                format!("Отменено: ошибка при обработке {}", addon.name)
+            };
+            cancel_steps(&steps[position + 1..], &reason);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn cancel_steps(steps: &[Step], reason: &str) {
+    for (addon, state, operation) in steps {
+        let mut state = state.lock().unwrap();
+        state.installing = false;
+        state.last_operation = Some(*operation);
+        state.target_state = Some(addon_manager::check_addon_installed(addon));
+        state.error = Some(reason.to_string());
+    }
+}
+
+pub fn run(
+    client: &Agent,
+    addon: &Addon,
+    state: &Arc<Mutex<AddonState>>,
+    operation: Operation,
+) -> Result<()> {
+    let mut state_lock = state.lock().unwrap();
+    let token = state_lock.cancel.clone();
+    state_lock.installing = true;
+    state_lock.last_operation = Some(operation);
+    state_lock.target_state = Some(operation.installs());
+    state_lock.progress = 0.0;
+    state_lock.downloaded = 0;
+    state_lock.total = None;
+    state_lock.speed = 0.0;
+    state_lock.error = None;
+    drop(state_lock);
+
+    let result = token
+        .check()
+        .and_then(|()| match operation {
+            Operation::Install => addon_manager::install_addon(client, addon, state.clone()),
+            Operation::Uninstall => addon_manager::uninstall_addon(addon),
+        })
+        .and_then(|success| {
+            if success {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("⚠ Operation incomplete: {}", addon.name))
+            }
+        });
+
+    if let Err(e) = addon_manager::refresh_update_state(client, addon, state) {
+        error!("Version check failed: {} - {}", addon.name, e);
+    }
+
+    let mut state = state.lock().unwrap();
+    state.installing = false;
+    state.target_state = Some(addon_manager::check_addon_installed(addon));
+
+    if let Err(e) = &result {
+        error!("Operation failed: {} - {:?}", addon.name, e);
+        state.error = Some(describe_error(e));
+    }
+
+    result
+}
+
+// Понятное пользователю описание; подробности остаются в журнале
+pub fn describe_error(error: &anyhow::Error) -> String {
+    for cause in error.chain() {
+        if let Some(cancelled) = cause.downcast_ref::<Cancelled>() {
+            return cancelled.to_string();
+        }
+
+        if let Some(e) = cause.downcast_ref::<ureq::Error>() {
+            return match e {
+                ureq::Error::Status(404, _) => "Файл не найден на сервере (404)".to_string(),
+                ureq::Error::Status(code, _) => format!("Сервер вернул ошибку {}", code),
+                ureq::Error::Transport(_) => {
+                    "Не удалось связаться с сервером, проверьте подключение к интернету".to_string()
+                }
+            };
+        }
+
+        if cause.is::<zip::result::ZipError>() {
+            return "Скачанный архив повреждён, попробуйте ещё раз".to_string();
+        }
+
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            match e.kind() {
+                ErrorKind::PermissionDenied => {
+                    return "Нет доступа к файлам игры: закройте игру и попробуйте снова"
+                        .to_string()
+                }
+                ErrorKind::TimedOut => return "Сервер не отвечает".to_string(),
+                ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::UnexpectedEof => {
+                    return "Соединение прервано, попробуйте ещё раз".to_string()
+                }
+                _ => {}
+            }
+        }
+    }
+
+    error.to_string()
+}
@@ -0,0 +1,480 @@
+use crate::app::Addon;
+use crate::config;
+use crate::modules::operations::{self, Step};
+use crate::modules::{cancellation, ledger};
+use log::{info, warn};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use ureq::Agent;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+#[derive(Clone)]
+pub struct Job {
+    pub id: u64,
+    pub title: String,
+    pub status: JobStatus,
+}
+
+// Что задача затрагивает: аддоны, их зависимости и пути, куда они ставятся
+#[derive(Clone)]
+struct Claim {
+    names: Vec<String>,
+    requires: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl Claim {
+    fn of(steps: &[Step]) -> Self {
+        Self {
+            names: steps
+                .iter()
+                .map(|(addon, _, _)| addon.name.clone())
+                .collect(),
+            requires: steps
+                .iter()
+                .flat_map(|(addon, _, _)| addon.depends.iter().cloned())
+                .collect(),
+            paths: steps
+                .iter()
+                .flat_map(|(addon, _, _)| install_paths(addon))
+                .collect(),
+        }
+    }
+
+    fn depends_on(&self, other: &Claim) -> bool {
+        self.requires.iter().any(|name| other.names.contains(name))
+    }
+
+    fn overlaps(&self, other: &Claim) -> bool {
+        let shares_addon = self.names.iter().any(|name| other.names.contains(name));
+        let shares_path = self.paths.iter().any(|path| {
+            other
+                .paths
+                .iter()
+                .any(|other| ledger::overlaps(path, other))
+        });
+
+        shares_addon || shares_path || self.depends_on(other) || other.depends_on(self)
+    }
+}
+
+// Папка аддона и всё, что записано за ним в реестре. Общая target_path
+// (обычно Interface/AddOns) сама по себе задачи не блокирует: папки, которые
+// появятся только после распаковки, на время установки занимает staging::lock_paths.
+fn install_paths(addon: &Addon) -> Vec<PathBuf> {
+    let mut paths = vec![config::base_dir()
+        .join(&addon.target_path)
+        .join(&addon.name)];
+    if let Some(entry) = ledger::entry(&addon.name) {
+        paths.extend(entry.absolute_paths());
+    }
+    paths
+}
+
+struct Scheduled {
+    id: u64,
+    claim: Claim,
+    steps: Vec<Step>,
+}
+
+struct State {
+    jobs: Vec<Job>,
+    pending: Vec<Scheduled>,
+    running: Vec<(u64, Claim)>,
+    limit: usize,
+    workers: usize,
+    next_id: u64,
+}
+
+impl State {
+    // Связанные задачи выполняются строго в порядке добавления, остальные — параллельно
+    fn next_runnable(&self) -> Option<usize> {
+        if self.running.len() >= self.limit {
+            return None;
+        }
+
+        (0..self.pending.len()).find(|&index| {
+            let claim = &self.pending[index].claim;
+            !self
+                .running
+                .iter()
+                .any(|(_, running)| running.overlaps(claim))
+                && !self.pending[..index]
+                    .iter()
+                    .any(|earlier| earlier.claim.overlaps(claim))
+        })
+    }
+
+    fn start(&mut self, index: usize) -> Scheduled {
+        let job = self.pending.remove(index);
+        self.running.push((job.id, job.claim.clone()));
+        self.set_status(job.id, JobStatus::Running);
+        job
+    }
+
+    // Возвращает задачи, отменённые из-за того, что не выполнилась их зависимость
+    fn finish(&mut self, id: u64, status: JobStatus) -> Vec<(Scheduled, String)> {
+        let Some(position) = self.running.iter().position(|(running, _)| *running == id) else {
+            return Vec::new();
+        };
+        let (_, claim) = self.running.remove(position);
+        let failed = status != JobStatus::Done;
+        self.set_status(id, status);
+
+        if !failed {
+            return Vec::new();
+        }
+
+        let mut failed_claims = vec![(claim, self.title(id))];
+        let mut cancelled = Vec::new();
+        let mut index = 0;
+
+        while index < self.pending.len() {
+            let blocker = failed_claims
+                .iter()
+                .find(|(failed, _)| self.pending[index].claim.depends_on(failed))
+                .map(|(_, title)| title.clone());
+
+            let Some(blocker) = blocker else {
+                index += 1;
+                continue;
+            };
+
+            let job = self.pending.remove(index);
+            let reason = format!("Отменено: не выполнено «{}»", blocker);
+            self.set_status(job.id, JobStatus::Failed(reason.clone()));
+            failed_claims.push((job.claim.clone(), self.title(job.id)));
+            cancelled.push((job, reason));
+        }
+
+        cancelled
+    }
+
+    fn title(&self, id: u64) -> String {
+        self.jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.title.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_status(&mut self, id: u64, status: JobStatus) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.status = status;
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+// Пул фоновых потоков с ограничением на число одновременных операций
+pub struct OperationQueue {
+    client: Agent,
+    shared: Arc<Shared>,
+}
+
+impl OperationQueue {
+    pub fn new(client: Agent, limit: usize) -> Self {
+        Self {
+            client,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    jobs: Vec::new(),
+                    pending: Vec::new(),
+                    running: Vec::new(),
+                    limit: limit.max(1),
+                    workers: 0,
+                    next_id: 0,
+                }),
+                wakeup: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn push(&self, title: String, steps: Vec<Step>) {
+        if steps.is_empty() {
+            return;
+        }
+
+        info!("Queued: {}", title);
+        for (_, state, _) in &steps {
+            state.lock().unwrap().cancel.reset();
+        }
+        operations::mark_pending(&steps);
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.jobs.push(Job {
+            id,
+            title,
+            status: JobStatus::Queued,
+        });
+        state.pending.push(Scheduled {
+            id,
+            claim: Claim::of(&steps),
+            steps,
+        });
+        self.spawn_workers(&mut state);
+        drop(state);
+
+        self.shared.wakeup.notify_all();
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.limit = limit.max(1);
+        self.spawn_workers(&mut state);
+        drop(state);
+
+        self.shared.wakeup.notify_all();
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.shared.state.lock().unwrap().jobs.clone()
+    }
+
+    pub fn clear_finished(&self) {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .jobs
+            .retain(|job| !job.status.is_finished());
+    }
+
+    fn spawn_workers(&self, state: &mut State) {
+        while state.workers < state.limit {
+            state.workers += 1;
+            let shared = self.shared.clone();
+            let client = self.client.clone();
+            std::thread::spawn(move || worker(&shared, &client));
+        }
+    }
+}
+
+fn worker(shared: &Shared, client: &Agent) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if let Some(index) = state.next_runnable() {
+                    break state.start(index);
+                }
+                state = shared.wakeup.wait(state).unwrap();
+            }
+        };
+
+        let status = match operations::run_sequence(client, &job.steps) {
+            Ok(()) => JobStatus::Done,
+            Err(e) if cancellation::is_cancelled(&e) => JobStatus::Cancelled,
+            Err(e) => JobStatus::Failed(e.to_string()),
+        };
+
+        let cancelled = shared.state.lock().unwrap().finish(job.id, status);
+        for (job, reason) in cancelled {
+            warn!("Job {} cancelled: {}", job.id, reason);
+            operations::cancel_steps(&job.steps, &reason);
+        }
+
+        shared.wakeup.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(names: &[&str], requires: &[&str], paths: &[&str]) -> Claim {
+        let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        Claim {
+            names: strings(names),
+            requires: strings(requires),
+            paths: paths.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    fn state(limit: usize) -> State {
+        State {
+            jobs: Vec::new(),
+            pending: Vec::new(),
+            running: Vec::new(),
+            limit,
+            workers: 0,
+            next_id: 0,
+        }
+    }
+
+    fn schedule(state: &mut State, title: &str, claim: Claim) -> u64 {
+        state.next_id += 1;
+        let id = state.next_id;
+        state.jobs.push(Job {
+            id,
+            title: title.to_string(),
+            status: JobStatus::Queued,
+        });
+        state.pending.push(Scheduled {
+            id,
+            claim,
+            steps: Vec::new(),
+        });
+        id
+    }
+
+    fn start_next(state: &mut State) -> Option<u64> {
+        state.next_runnable().map(|index| state.start(index).id)
+    }
+
+    fn status(state: &State, id: u64) -> JobStatus {
+        state
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .unwrap()
+            .status
+            .clone()
+    }
+
+    #[test]
+    fn separate_folders_in_target_path_run_in_parallel() {
+        let mut state = state(2);
+        let a = schedule(&mut state, "A", claim(&["A"], &[], &["AddOns/A"]));
+        let b = schedule(&mut state, "B", claim(&["B"], &[], &["AddOns/B"]));
+
+        assert_eq!(start_next(&mut state), Some(a));
+        assert_eq!(start_next(&mut state), Some(b));
+        assert_eq!(status(&state, b), JobStatus::Running);
+    }
+
+    #[test]
+    fn folder_recorded_for_both_addons_serializes_jobs() {
+        let mut state = state(2);
+        let a = schedule(
+            &mut state,
+            "A",
+            claim(&["A"], &[], &["AddOns/A", "AddOns/LibStub"]),
+        );
+        schedule(
+            &mut state,
+            "B",
+            claim(&["B"], &[], &["AddOns/B", "AddOns/LibStub"]),
+        );
+
+        assert_eq!(start_next(&mut state), Some(a));
+        assert_eq!(start_next(&mut state), None);
+    }
+
+    #[test]
+    fn respects_limit() {
+        let mut state = state(1);
+        let a = schedule(&mut state, "A", claim(&["A"], &[], &["AddOns/A"]));
+        schedule(&mut state, "B", claim(&["B"], &[], &["AddOns/B"]));
+
+        assert_eq!(start_next(&mut state), Some(a));
+        assert_eq!(start_next(&mut state), None);
+    }
+
+    #[test]
+    fn overlapping_paths_wait() {
+        let mut state = state(4);
+        let a = schedule(&mut state, "A", claim(&["A"], &[], &["AddOns/A"]));
+        schedule(
+            &mut state,
+            "A_Sub",
+            claim(&["A_Sub"], &[], &["AddOns/A/Sub"]),
+        );
+
+        assert_eq!(start_next(&mut state), Some(a));
+        assert_eq!(start_next(&mut state), None);
+    }
+
+    #[test]
+    fn related_jobs_keep_order() {
+        let mut state = state(4);
+        let lib = schedule(&mut state, "Lib", claim(&["Lib"], &[], &["AddOns/Lib"]));
+        // Ждёт Lib, потому что тоже пишет в её папку
+        let patch = schedule(&mut state, "Patch", claim(&["Patch"], &[], &["AddOns/Lib"]));
+        // Свободен сам по себе, но зависит от Patch, добавленного раньше
+        let ui = schedule(&mut state, "UI", claim(&["UI"], &["Patch"], &["AddOns/UI"]));
+        let other = schedule(
+            &mut state,
+            "Other",
+            claim(&["Other"], &[], &["AddOns/Other"]),
+        );
+
+        assert_eq!(start_next(&mut state), Some(lib));
+        assert_eq!(start_next(&mut state), Some(other));
+        assert_eq!(start_next(&mut state), None);
+
+        assert!(state.finish(lib, JobStatus::Done).is_empty());
+        assert_eq!(start_next(&mut state), Some(patch));
+        assert_eq!(start_next(&mut state), None);
+
+        assert!(state.finish(patch, JobStatus::Done).is_empty());
+        assert_eq!(start_next(&mut state), Some(ui));
+    }
+
+    #[test]
+    fn failure_cancels_dependents() {
+        let mut state = state(1);
+        let lib = schedule(&mut state, "Lib", claim(&["Lib"], &[], &["AddOns/Lib"]));
+        let ui = schedule(&mut state, "UI", claim(&["UI"], &["Lib"], &["AddOns/UI"]));
+        let skin = schedule(
+            &mut state,
+            "Skin",
+            claim(&["Skin"], &["UI"], &["AddOns/Skin"]),
+        );
+        let other = schedule(
+            &mut state,
+            "Other",
+            claim(&["Other"], &[], &["AddOns/Other"]),
+        );
+
+        assert_eq!(start_next(&mut state), Some(lib));
+        let cancelled = state.finish(lib, JobStatus::Failed("boom".to_string()));
+
+        let ids: Vec<u64> = cancelled.iter().map(|(job, _)| job.id).collect();
+        assert_eq!(ids, vec![ui, skin]);
+        assert_eq!(
+            status(&state, ui),
+            JobStatus::Failed("Отменено: не выполнено «Lib»".to_string())
+        );
+        assert_eq!(
+            status(&state, skin),
+            JobStatus::Failed("Отменено: не выполнено «UI»".to_string())
+        );
+        assert_eq!(status(&state, other), JobStatus::Queued);
+        assert_eq!(start_next(&mut state), Some(other));
+    }
+
+    #[test]
+    fn cancelled_job_also_cancels_dependents() {
+        let mut state = state(1);
+        let lib = schedule(&mut state, "Lib", claim(&["Lib"], &[], &["AddOns/Lib"]));
+        let ui = schedule(&mut state, "UI", claim(&["UI"], &["Lib"], &["AddOns/UI"]));
+
+        start_next(&mut state);
+        let cancelled = state.finish(lib, JobStatus::Cancelled);
+
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].0.id, ui);
+        assert_eq!(status(&state, lib), JobStatus::Cancelled);
+    }
+}
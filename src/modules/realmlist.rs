@@ -0,0 +1,114 @@
+use crate::config;
+use anyhow::{Context, Result};
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "realmlist.wtf";
+const BACKUP_SUFFIX: &str = ".bak";
+
+// Файлы Data/<локаль>/realmlist.wtf, например Data/ruRU/realmlist.wtf
+pub fn files() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(config::base_dir().join("Data")) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.path().is_dir() && is_locale(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path().join(FILE_NAME))
+        .collect();
+    files.sort();
+    files
+}
+
+fn is_locale(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 4
+        && bytes[..2].iter().all(u8::is_ascii_lowercase)
+        && bytes[2..].iter().all(u8::is_ascii_uppercase)
+}
+
+pub fn current() -> Option<String> {
+    files().iter().find_map(|path| {
+        let text = fs::read_to_string(path).ok()?;
+        text.lines().find_map(parse_line)
+    })
+}
+
+fn parse_line(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    let set = words.next()?;
+    let key = words.next()?;
+    if !set.eq_ignore_ascii_case("set") || !key.eq_ignore_ascii_case("realmlist") {
+        return None;
+    }
+
+    let value = words.collect::<Vec<_>>().join(" ");
+    Some(value.trim_matches('"').to_string())
+}
+
+// Меняет только строку "set realmlist", остальные строки файла сохраняются.
+// Прежний файл остаётся рядом с расширением .bak.
+pub fn apply(address: &str) -> Result<()> {
+    let address = address.trim();
+    if address.is_empty() {
+        return Err(anyhow::anyhow!("Адрес сервера не указан"));
+    }
+
+    let files = files();
+    if files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Не найдена папка локализации в {}",
+            config::base_dir().join("Data").display()
+        ));
+    }
+
+    for path in files {
+        write_file(&path, address)
+            .with_context(|| format!("🔴 Failed to update {}", path.display()))?;
+    }
+
+    info!("Realmlist set to {}", address);
+    Ok(())
+}
+
+fn write_file(path: &Path, address: &str) -> Result<()> {
+    let previous = fs::read_to_string(path).unwrap_or_default();
+    let line = format!("set realmlist {}", address);
+
+    if previous
+        .lines()
+        .filter_map(parse_line)
+        .any(|value| value == address)
+    {
+        return Ok(());
+    }
+
+    if path.exists() {
+        let mut backup = path.as_os_str().to_os_string();
+        backup.push(BACKUP_SUFFIX);
+        fs::copy(path, &backup)?;
+    }
+
+    let mut replaced = false;
+    let mut lines: Vec<String> = previous
+        .lines()
+        .filter_map(|existing| match parse_line(existing) {
+            Some(_) if replaced => None,
+            Some(_) => {
+                replaced = true;
+                Some(line.clone())
+            }
+            None => Some(existing.to_string()),
+        })
+        .collect();
+    if !replaced {
+        lines.insert(0, line);
+    }
+
+    let temp = path.with_extension("wtf.tmp");
+    fs::write(&temp, lines.join("\r\n") + "\r\n")?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::app::AddonState;
+use crate::modules::cancellation::CancellationToken;
+use crate::modules::{downloader, staging};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use ureq::Agent;
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    assets: HashMap<String, Asset>,
+}
+
+#[derive(Clone, Deserialize)]
+struct Asset {
+    url: String,
+    sha256: String,
+}
+
+impl Release {
+    // Сборки в ленте выпусков разложены по std::env::consts::OS
+    fn asset(&self) -> Option<&Asset> {
+        self.assets.get(env::consts::OS)
+    }
+}
+
+#[derive(Clone)]
+pub enum UpdateStatus {
+    Idle,
+    Available(Release),
+    Installing,
+    Installed(String),
+    Failed(String),
+}
+
+pub fn check(client: &Agent, feed_url: &str) -> Result<Option<Release>> {
+    let response = match client
+        .get(feed_url)
+        .set("User-Agent", "NightWatchUpdater/1.0")
+        .call()
+    {
+        Ok(response) => response,
+        // Выпусков с лентой ещё нет — обновляться не на что
+        Err(ureq::Error::Status(404, _)) => {
+            info!("No release feed published at {}", feed_url);
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let text = response.into_string()?;
+    let release: Release =
+        serde_json::from_str(&text).context("🔧 Failed to parse release feed")?;
+
+    if !is_newer(&release.version, CURRENT_VERSION) {
+        return Ok(None);
+    }
+
+    if release.asset().is_none() {
+        warn!(
+            "Release {} has no build for {}",
+            release.version,
+            env::consts::OS
+        );
+        return Ok(None);
+    }
+
+    info!("Updater {} is available", release.version);
+    Ok(Some(release))
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    parse_version(candidate) > parse_version(current)
+}
+
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+// Новая версия скачивается рядом с текущей и подменяет её переименованием.
+// Работающий файл остаётся как .old для отката.
+pub fn install(
+    client: &Agent,
+    release: &Release,
+    state: Arc<Mutex<AddonState>>,
+    token: &CancellationToken,
+) -> Result<()> {
+    let asset = release.asset().context("🔴 No build for this platform")?;
+    let exe = current_exe()?;
+    let new_path = downloader::with_suffix(&exe, ".new");
+
+    downloader::download_file(client, &asset.url, &new_path, state, token)?;
+    if let Err(e) = downloader::verify_sha256(&new_path, &asset.sha256) {
+        let _ = fs::remove_file(&new_path);
+        return Err(e);
+    }
+
+    fs::set_permissions(&new_path, fs::metadata(&exe)?.permissions())?;
+    replace_executable(&exe, &new_path)?;
+    info!("✅ Updater {} installed", release.version);
+    Ok(())
+}
+
+pub fn has_previous_version() -> bool {
+    current_exe().is_ok_and(|exe| old_path(&exe).exists())
+}
+
+pub fn rollback() -> Result<()> {
+    let exe = current_exe()?;
+    let old = old_path(&exe);
+    if !old.exists() {
+        return Err(anyhow::anyhow!("Предыдущая версия не найдена"));
+    }
+
+    let rolled_back = downloader::with_suffix(&exe, ".new");
+    staging::remove_path(&rolled_back)?;
+    fs::rename(&exe, &rolled_back).context("🔴 Failed to move current version aside")?;
+
+    if let Err(e) = fs::rename(&old, &exe) {
+        let _ = fs::rename(&rolled_back, &exe);
+        return Err(e).context("🔴 Failed to restore previous version");
+    }
+
+    info!("↩ Updater rolled back to the previous version");
+    Ok(())
+}
+
+// Остатки неудачной загрузки или отката
+pub fn cleanup() {
+    if let Ok(exe) = current_exe() {
+        let _ = staging::remove_path(&downloader::with_suffix(&exe, ".new"));
+    }
+}
+
+pub fn restart() -> Result<()> {
+    let exe = current_exe()?;
+    Command::new(&exe)
+        .args(env::args_os().skip(1))
+        .spawn()
+        .with_context(|| format!("🔴 Failed to start {}", exe.display()))?;
+    Ok(())
+}
+
+fn replace_executable(exe: &Path, new_path: &Path) -> Result<()> {
+    let old = old_path(exe);
+    staging::remove_path(&old)?;
+    fs::rename(exe, &old).context("🔴 Failed to move current version aside")?;
+
+    if let Err(e) = fs::rename(new_path, exe) {
+        let _ = fs::rename(&old, exe);
+        return Err(e).context("🔴 Failed to install new version");
+    }
+
+    Ok(())
+}
+
+fn current_exe() -> Result<PathBuf> {
+    env::current_exe().context("🔴 Failed to locate the updater executable")
+}
+
+fn old_path(exe: &Path) -> PathBuf {
+    downloader::with_suffix(exe, ".old")
+}
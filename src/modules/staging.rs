@@ -0,0 +1,359 @@
+use crate::modules::cancellation::{self, CancellationToken};
+use crate::modules::ledger;
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+const STAGED_SUFFIX: &str = ".nw-staged";
+const BACKUP_SUFFIX: &str = ".nw-backup";
+
+static LOCKED_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+static PATHS_RELEASED: Condvar = Condvar::new();
+
+// Пути, занятые одной установкой или удалением, до конца операции вместе с записью в реестр
+pub struct PathLock {
+    paths: Vec<PathBuf>,
+}
+
+impl Drop for PathLock {
+    fn drop(&mut self) {
+        let mut locked = LOCKED_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+        for path in &self.paths {
+            if let Some(position) = locked.iter().position(|locked| locked == path) {
+                locked.remove(position);
+            }
+        }
+        drop(locked);
+        PATHS_RELEASED.notify_all();
+    }
+}
+
+// Что именно запишет архив, становится известно только после распаковки, поэтому
+// очередь не может развести такие задачи заранее. Здесь ждём, пока пути освободятся.
+pub fn lock_paths(paths: Vec<PathBuf>, token: &CancellationToken) -> Result<PathLock> {
+    let mut locked = LOCKED_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+
+    loop {
+        token.check()?;
+
+        let busy = paths
+            .iter()
+            .any(|path| locked.iter().any(|other| ledger::overlaps(path, other)));
+        if !busy {
+            locked.extend(paths.iter().cloned());
+            return Ok(PathLock { paths });
+        }
+
+        locked = PATHS_RELEASED
+            .wait_timeout(locked, Duration::from_millis(200))
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+}
+
+struct Swap {
+    source: PathBuf,
+    target: PathBuf,
+    staged: PathBuf,
+    backup: PathBuf,
+    backed_up: bool,
+    swapped: bool,
+}
+
+impl Swap {
+    fn new(source: PathBuf, target: PathBuf) -> Result<Self> {
+        let name = target
+            .file_name()
+            .context("🔴 Invalid install target")?
+            .to_string_lossy()
+            .into_owned();
+        let parent = target.parent().context("🔴 Invalid install target")?;
+
+        Ok(Self {
+            staged: parent.join(format!(".{}{}", name, STAGED_SUFFIX)),
+            backup: parent.join(format!(".{}{}", name, BACKUP_SUFFIX)),
+            source,
+            target,
+            backed_up: false,
+            swapped: false,
+        })
+    }
+
+    // Остатки прерванной установки: резервная копия без цели возвращается на место
+    fn recover_interrupted(&self) -> Result<()> {
+        if self.backup.exists() {
+            if self.target.exists() {
+                remove_path(&self.backup)?;
+            } else {
+                warn!("Restoring interrupted install: {}", self.target.display());
+                fs::rename(&self.backup, &self.target)?;
+            }
+        }
+        remove_path(&self.staged)
+    }
+
+    fn stage(&self, token: &CancellationToken) -> Result<()> {
+        self.recover_interrupted()?;
+
+        if let Some(parent) = self.target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if self.source.is_dir() {
+            info!(
+                "📁 Copying: [{}] -> [{}]",
+                self.source.display(),
+                self.staged.display()
+            );
+            copy_dir(&self.source, &self.staged, token)
+        } else {
+            copy_file(&self.source, &self.staged, token)
+        }
+    }
+
+    fn swap(&mut self) -> Result<()> {
+        if self.target.exists() {
+            fs::rename(&self.target, &self.backup)
+                .with_context(|| format!("🔴 Failed to move aside: {}", self.target.display()))?;
+            self.backed_up = true;
+        }
+
+        fs::rename(&self.staged, &self.target)
+            .with_context(|| format!("🔴 Failed to swap in: {}", self.target.display()))?;
+        self.swapped = true;
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        if self.swapped {
+            if let Err(e) = remove_path(&self.target) {
+                error!("Rollback failed to remove {}: {}", self.target.display(), e);
+                return;
+            }
+        }
+
+        if self.backed_up {
+            match fs::rename(&self.backup, &self.target) {
+                Ok(()) => info!("↩ Restored previous version: {}", self.target.display()),
+                Err(e) => error!(
+                    "Rollback failed to restore {}: {}",
+                    self.target.display(),
+                    e
+                ),
+            }
+        }
+
+        if let Err(e) = remove_path(&self.staged) {
+            warn!("Failed to clean staging {}: {}", self.staged.display(), e);
+        }
+    }
+}
+
+pub fn is_staging_artifact(name: &str) -> bool {
+    name.ends_with(STAGED_SUFFIX) || name.ends_with(BACKUP_SUFFIX)
+}
+
+// Копирует каждый источник рядом с целью, затем подменяет цели переименованием.
+// Старые версии удаляются только после успешной подмены всех записей.
+// Отмена возможна до подмены, пока установленная версия ещё не тронута.
+pub fn install_atomically(
+    entries: Vec<(PathBuf, PathBuf)>,
+    token: &CancellationToken,
+) -> Result<Vec<PathBuf>> {
+    let mut swaps = entries
+        .into_iter()
+        .map(|(source, target)| Swap::new(source, target))
+        .collect::<Result<Vec<_>>>()?;
+
+    let staged = swaps
+        .iter()
+        .try_for_each(|swap| swap.stage(token))
+        .and_then(|_| token.check());
+
+    if let Err(e) = staged {
+        error!("Install failed, rolling back: {}", e);
+        swaps.iter_mut().rev().for_each(Swap::rollback);
+        return Err(e);
+    }
+
+    swap_all(swaps)
+}
+
+fn swap_all(mut swaps: Vec<Swap>) -> Result<Vec<PathBuf>> {
+    if let Err(e) = swaps.iter_mut().try_for_each(Swap::swap) {
+        error!("Install failed, rolling back: {}", e);
+        swaps.iter_mut().rev().for_each(Swap::rollback);
+        return Err(e);
+    }
+
+    for swap in &swaps {
+        if swap.backed_up {
+            if let Err(e) = remove_path(&swap.backup) {
+                warn!("Failed to remove backup {}: {}", swap.backup.display(), e);
+            }
+        }
+    }
+
+    Ok(swaps.into_iter().map(|swap| swap.target).collect())
+}
+
+fn copy_dir(source: &Path, dest: &Path, token: &CancellationToken) -> Result<()> {
+    fs::create_dir_all(dest)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let target_path = dest.join(entry.file_name());
+
+        if entry_path.is_dir() {
+            copy_dir(&entry_path, &target_path, token)?;
+        } else {
+            copy_file(&entry_path, &target_path, token)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn copy_file(source: &Path, dest: &Path, token: &CancellationToken) -> Result<()> {
+    let mut reader =
+        File::open(source).with_context(|| format!("🔴 Failed to read: {}", source.display()))?;
+    let mut writer =
+        File::create(dest).with_context(|| format!("🔴 Failed to write: {}", dest.display()))?;
+
+    cancellation::copy(&mut reader, &mut writer, token)?;
+    fs::set_permissions(dest, reader.metadata()?.permissions())?;
+    Ok(())
+}
+
+pub fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn replaces_targets_and_removes_backups() {
+        let dir = tempdir().unwrap();
+        write(&dir.path().join("new/Addon/new.lua"), "new");
+        write(&dir.path().join("game/Addon/old.lua"), "old");
+
+        let target = dir.path().join("game/Addon");
+        let installed = install_atomically(
+            vec![(dir.path().join("new/Addon"), target.clone())],
+            &CancellationToken::default(),
+        )
+        .unwrap();
+
+        assert_eq!(installed, vec![target.clone()]);
+        assert!(target.join("new.lua").is_file());
+        assert!(!target.join("old.lua").exists());
+        assert_eq!(fs::read_dir(dir.path().join("game")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn rollback_restores_previous_folder_when_later_swap_fails() {
+        let dir = tempdir().unwrap();
+        write(&dir.path().join("new/First/new.lua"), "new");
+        write(&dir.path().join("new/Second/new.lua"), "new");
+        write(&dir.path().join("game/First/old.lua"), "old");
+        write(&dir.path().join("game/Second/old.lua"), "old");
+
+        let swaps = vec![
+            Swap::new(dir.path().join("new/First"), dir.path().join("game/First")).unwrap(),
+            Swap::new(
+                dir.path().join("new/Second"),
+                dir.path().join("game/Second"),
+            )
+            .unwrap(),
+        ];
+        let token = CancellationToken::default();
+        for swap in &swaps {
+            swap.stage(&token).unwrap();
+        }
+        // Вторая подмена не найдёт подготовленную копию
+        remove_path(&swaps[1].staged).unwrap();
+
+        assert!(swap_all(swaps).is_err());
+        for name in ["First", "Second"] {
+            let target = dir.path().join("game").join(name);
+            assert_eq!(fs::read_to_string(target.join("old.lua")).unwrap(), "old");
+            assert!(!target.join("new.lua").exists());
+        }
+        assert_eq!(fs::read_dir(dir.path().join("game")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn cancel_before_swap_keeps_installed_version() {
+        let dir = tempdir().unwrap();
+        write(&dir.path().join("new/Addon/new.lua"), "new");
+        write(&dir.path().join("game/Addon/old.lua"), "old");
+
+        let token = CancellationToken::default();
+        token.cancel();
+        let result = install_atomically(
+            vec![(dir.path().join("new/Addon"), dir.path().join("game/Addon"))],
+            &token,
+        );
+
+        assert!(result.is_err());
+        assert!(dir.path().join("game/Addon/old.lua").is_file());
+        assert_eq!(fs::read_dir(dir.path().join("game")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn lock_waits_for_overlapping_paths() {
+        let dir = tempdir().unwrap();
+        let lib = dir.path().join("AddOns/LibStub");
+        let first = lock_paths(vec![lib.clone()], &CancellationToken::default()).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let waiting = std::thread::spawn(move || {
+            let lock = lock_paths(vec![lib.join("LibStub.lua")], &CancellationToken::default());
+            sender.send(lock.is_ok()).unwrap();
+        });
+
+        assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+        drop(first);
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+        waiting.join().unwrap();
+    }
+
+    #[test]
+    fn lock_allows_separate_paths() {
+        let dir = tempdir().unwrap();
+        let token = CancellationToken::default();
+        let _first = lock_paths(vec![dir.path().join("AddOns/A")], &token).unwrap();
+        let _second = lock_paths(vec![dir.path().join("AddOns/B")], &token).unwrap();
+    }
+
+    #[test]
+    fn lock_wait_is_cancellable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("AddOns/A");
+        let _first = lock_paths(vec![path.clone()], &CancellationToken::default()).unwrap();
+
+        let token = CancellationToken::default();
+        token.cancel();
+        assert!(lock_paths(vec![path], &token).is_err());
+    }
+}